@@ -0,0 +1,11 @@
+use color_backtrace::{default_output_stream, BacktracePrinter, PreviousHook};
+
+fn main() {
+    std::panic::set_hook(Box::new(|_| eprintln!("\n(metrics hook: panic recorded)")));
+
+    BacktracePrinter::new()
+        .previous_hook(PreviousHook::CallAfter)
+        .install(default_output_stream());
+
+    "x".parse::<u32>().unwrap();
+}
@@ -0,0 +1,37 @@
+use color_backtrace::{default_output_stream, BacktracePrinter};
+use std::backtrace::Backtrace;
+use std::fmt;
+
+#[derive(Debug)]
+struct ConfigError {
+    source: std::io::Error,
+    backtrace: Backtrace,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed to load configuration")
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+fn load_config() -> Result<String, ConfigError> {
+    std::fs::read_to_string("/does/not/exist.toml").map_err(|source| ConfigError {
+        source,
+        backtrace: Backtrace::force_capture(),
+    })
+}
+
+fn main() {
+    if let Err(e) = load_config() {
+        BacktracePrinter::new()
+            .print_error_with_trace(&e, &e.backtrace, &mut default_output_stream())
+            .unwrap();
+        std::process::exit(1);
+    }
+}
@@ -26,6 +26,7 @@ fn main() {
     fn1();
 }
 
+#[allow(clippy::unnecessary_literal_unwrap)]
 fn fn5() {
     // Source printing at the end of a file
     Err::<(), ()>(()).unwrap();
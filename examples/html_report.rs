@@ -0,0 +1,10 @@
+use color_backtrace::{BacktracePrinter, Verbosity};
+
+fn main() -> Result<(), std::io::Error> {
+    let trace = backtrace::Backtrace::new();
+    let printer = BacktracePrinter::new().lib_verbosity(Verbosity::Full);
+    let html = printer.format_trace_to_html_string(&trace)?;
+    std::fs::write("backtrace.html", html)?;
+    println!("Report written to backtrace.html");
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use color_backtrace::BacktracePrinter;
+
+fn main() {
+    let handler = BacktracePrinter::new().into_json_panic_handler(std::io::stderr());
+    std::panic::set_hook(handler);
+    "x".parse::<u32>().unwrap();
+}
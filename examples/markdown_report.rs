@@ -0,0 +1,11 @@
+use color_backtrace::BacktracePrinter;
+
+fn main() {
+    std::panic::set_hook(Box::new(|pi| {
+        let printer = BacktracePrinter::new();
+        if let Err(e) = printer.print_panic_info_markdown(pi, &mut std::io::stdout()) {
+            eprintln!("Error while printing panic: {:?}", e);
+        }
+    }));
+    "x".parse::<u32>().unwrap();
+}
@@ -0,0 +1,17 @@
+#[inline(never)]
+fn parse_expr(depth: u32) -> u32 {
+    parse_term(depth) + 1
+}
+
+#[inline(never)]
+fn parse_term(depth: u32) -> u32 {
+    if depth == 0 {
+        panic!("unexpected end of input");
+    }
+    parse_expr(depth - 1) + 1
+}
+
+fn main() {
+    color_backtrace::install();
+    parse_expr(500);
+}
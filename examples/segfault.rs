@@ -0,0 +1,16 @@
+use color_backtrace::{default_output_stream, BacktracePrinter};
+
+fn fn2() {
+    unsafe { std::ptr::null_mut::<u32>().write_volatile(1) };
+}
+
+fn fn1() {
+    fn2();
+}
+
+fn main() {
+    BacktracePrinter::new()
+        .install_signal_handler(default_output_stream())
+        .unwrap();
+    fn1();
+}
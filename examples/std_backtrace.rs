@@ -0,0 +1,15 @@
+use color_backtrace::{default_output_stream, BacktracePrinter};
+use std::backtrace::Backtrace;
+
+fn fn2() -> Backtrace {
+    Backtrace::force_capture()
+}
+
+fn fn1() -> Backtrace {
+    fn2()
+}
+
+fn main() -> Result<(), std::io::Error> {
+    let trace = fn1();
+    BacktracePrinter::new().print_std_trace(&trace, &mut default_output_stream())
+}
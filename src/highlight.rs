@@ -0,0 +1,300 @@
+//! A minimal Rust tokenizer for highlighting source snippets.
+//!
+//! This doesn't attempt to fully lex Rust, it only recognizes the tokens that
+//! are highlighted. Snippets are highlighted line by line, so block comments
+//! and strings spanning multiple lines are tracked across calls.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Lifetime,
+    Macro,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StringKind {
+    Normal,
+    /// A raw string terminated by `"` and this many `#`.
+    Raw(usize),
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+#[derive(Debug, Default)]
+pub(crate) struct Highlighter {
+    comment_depth: usize,
+    string: Option<StringKind>,
+}
+
+impl Highlighter {
+    /// Split a line into highlighted tokens.
+    pub(crate) fn highlight<'a>(&mut self, line: &'a str) -> Vec<(Token, &'a str)> {
+        let mut tokens: Vec<(Token, &str)> = vec![];
+        let mut start = 0;
+        while start < line.len() {
+            let (token, len) = self.next_token(&line[start..]);
+            match tokens.last_mut() {
+                // Merge runs of plain characters.
+                Some((last, text)) if *last == token && token == Token::Plain => {
+                    *text = &line[start - text.len()..start + len];
+                }
+                _ => tokens.push((token, &line[start..start + len])),
+            }
+            start += len;
+        }
+        tokens
+    }
+
+    /// Determine the kind and length of the token at the start of `rest`.
+    fn next_token(&mut self, rest: &str) -> (Token, usize) {
+        if self.comment_depth > 0 {
+            return (Token::Comment, self.block_comment_len(rest));
+        }
+        if let Some(kind) = self.string {
+            return (Token::String, self.string_len(rest, kind));
+        }
+
+        if rest.starts_with("//") {
+            return (Token::Comment, rest.len());
+        }
+        if let Some(after) = rest.strip_prefix("/*") {
+            self.comment_depth = 1;
+            return (Token::Comment, 2 + self.block_comment_len(after));
+        }
+
+        // String literals, including byte, C and raw strings.
+        for prefix in ["br", "cr", "r", "b", "c", ""] {
+            let after = match rest.strip_prefix(prefix) {
+                Some(after) => after,
+                None => continue,
+            };
+            let (kind, hashes) = if prefix.ends_with('r') {
+                let hashes = after.len() - after.trim_start_matches('#').len();
+                (StringKind::Raw(hashes), hashes)
+            } else {
+                (StringKind::Normal, 0)
+            };
+            if after[hashes..].starts_with('"') {
+                let start = prefix.len() + hashes + 1;
+                self.string = Some(kind);
+                return (Token::String, start + self.string_len(&rest[start..], kind));
+            }
+        }
+
+        // Character literals and lifetimes.
+        if let Some(after) = rest.strip_prefix('\'').or_else(|| rest.strip_prefix("b'")) {
+            let offset = rest.len() - after.len();
+            match after.chars().next() {
+                // The escaped character is never the closing quote.
+                Some('\\') => {
+                    if let Some(end) = after.get(2..).and_then(|x| x.find('\'')) {
+                        return (Token::String, offset + end + 3);
+                    }
+                }
+                Some(c) if after[c.len_utf8()..].starts_with('\'') => {
+                    return (Token::String, offset + c.len_utf8() + 1);
+                }
+                Some(c) if offset == 1 && is_ident_start(c) => {
+                    return (Token::Lifetime, 1 + ident_len(after));
+                }
+                _ => {}
+            }
+        }
+
+        if rest.starts_with(is_ident_start) {
+            let len = ident_len(rest);
+            let after = &rest[len..];
+            if after.starts_with('!') && !after.starts_with("!=") {
+                return (Token::Macro, len + 1);
+            }
+            if KEYWORDS.contains(&&rest[..len]) {
+                return (Token::Keyword, len);
+            }
+            return (Token::Plain, len);
+        }
+
+        (Token::Plain, rest.chars().next().map_or(1, char::len_utf8))
+    }
+
+    /// Length of the block comment text at the start of `rest`, up to and
+    /// including the end of the outermost comment.
+    fn block_comment_len(&mut self, rest: &str) -> usize {
+        let mut i = 0;
+        while i < rest.len() {
+            if rest[i..].starts_with("/*") {
+                self.comment_depth += 1;
+                i += 2;
+            } else if rest[i..].starts_with("*/") {
+                self.comment_depth -= 1;
+                i += 2;
+                if self.comment_depth == 0 {
+                    return i;
+                }
+            } else {
+                i += rest[i..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        rest.len()
+    }
+
+    /// Length of the string literal text at the start of `rest`, up to and
+    /// including its terminator.
+    fn string_len(&mut self, rest: &str, kind: StringKind) -> usize {
+        let end = match kind {
+            StringKind::Normal => {
+                let mut escaped = false;
+                rest.char_indices()
+                    .find(|&(_, c)| {
+                        let is_end = c == '"' && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        is_end
+                    })
+                    .map(|(i, _)| i + 1)
+            }
+            StringKind::Raw(hashes) => {
+                let terminator = format!("\"{}", "#".repeat(hashes));
+                rest.find(&terminator).map(|i| i + terminator.len())
+            }
+        };
+
+        match end {
+            Some(end) => {
+                self.string = None;
+                end
+            }
+            None => rest.len(),
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn ident_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(s.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Token::*;
+    use super::*;
+
+    /// Highlight consecutive lines of a snippet.
+    fn highlight<'a>(lines: &[&'a str]) -> Vec<Vec<(Token, &'a str)>> {
+        let mut highlighter = Highlighter::default();
+        lines.iter().map(|x| highlighter.highlight(x)).collect()
+    }
+
+    #[test]
+    fn raw_strings() {
+        assert_eq!(
+            highlight(&[r###"let s = r#"a "quoted" b"#;"###]),
+            [vec![
+                (Keyword, "let"),
+                (Plain, " s = "),
+                (String, r###"r#"a "quoted" b"#"###),
+                (Plain, ";"),
+            ]]
+        );
+        assert_eq!(
+            highlight(&[r###"br##"one "# still"###, r###"two"## + x"###]),
+            [
+                vec![(String, r###"br##"one "# still"###)],
+                vec![(String, r###"two"##"###), (Plain, " + x")],
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        assert_eq!(
+            highlight(&["a /* x /* y */ z */ b"]),
+            [vec![
+                (Plain, "a "),
+                (Comment, "/* x /* y */ z */"),
+                (Plain, " b"),
+            ]]
+        );
+        assert_eq!(
+            highlight(&["/* outer /* inner */", "still */ code // done"]),
+            [
+                vec![(Comment, "/* outer /* inner */")],
+                vec![
+                    (Comment, "still */"),
+                    (Plain, " code "),
+                    (Comment, "// done"),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn chars_and_lifetimes() {
+        assert_eq!(
+            highlight(&["fn f<'a>(x: &'a str) -> char { 'a' }"]),
+            [vec![
+                (Keyword, "fn"),
+                (Plain, " f<"),
+                (Lifetime, "'a"),
+                (Plain, ">(x: &"),
+                (Lifetime, "'a"),
+                (Plain, " str) -> char { "),
+                (String, "'a'"),
+                (Plain, " }"),
+            ]]
+        );
+        assert_eq!(
+            highlight(&[r"['\'', '\u{1F600}', 'static]"]),
+            [vec![
+                (Plain, "["),
+                (String, r"'\''"),
+                (Plain, ", "),
+                (String, r"'\u{1F600}'"),
+                (Plain, ", "),
+                (Lifetime, "'static"),
+                (Plain, "]"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn byte_chars() {
+        assert_eq!(
+            highlight(&[r"b'x' == b'\n' && b != a"]),
+            [vec![
+                (String, "b'x'"),
+                (Plain, " == "),
+                (String, r"b'\n'"),
+                (Plain, " && b != a"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn strings_across_lines() {
+        assert_eq!(
+            highlight(&[r#"let s = "a\"b"#, r#"c" + 1; println!("{}", s);"#]),
+            [
+                vec![(Keyword, "let"), (Plain, " s = "), (String, r#""a\"b"#)],
+                vec![
+                    (String, r#"c""#),
+                    (Plain, " + 1; "),
+                    (Macro, "println!"),
+                    (Plain, "("),
+                    (String, r#""{}""#),
+                    (Plain, ", s);"),
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Self-contained HTML crash reports.
+//!
+//! The report contains the same frames, hidden-frame markers and source snippets
+//! as the terminal output. The active [`ColorScheme`] is translated into an
+//! inline style sheet, and hidden frames are kept in collapsible `<details>`
+//! sections instead of being dropped.
+
+use crate::{
+    panic_payload, repetition_msg, BacktracePrinter, ColorScheme, Frame, IOResult, PanicHookInfo,
+    TraceRenderer, Verbosity,
+};
+use std::io::Write;
+use termcolor::{Color, ColorSpec};
+
+/// HTML output.
+impl BacktracePrinter {
+    /// Renders a [`backtrace::Backtrace`](backtrace::Backtrace) as an HTML document.
+    pub fn print_trace_html(&self, trace: &backtrace::Backtrace, out: &mut impl Write) -> IOResult {
+        self.write_html_head(out)?;
+        self.write_trace_html(trace, out)?;
+        write_html_tail(out)
+    }
+
+    /// Format a backtrace as an HTML document `String`.
+    pub fn format_trace_to_html_string(&self, trace: &backtrace::Backtrace) -> IOResult<String> {
+        let mut buf = vec![];
+        self.print_trace_html(trace, &mut buf)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    /// Renders a [`PanicInfo`](std::panic::PanicInfo) struct as an HTML document.
+    pub fn print_panic_info_html(&self, pi: &PanicHookInfo, out: &mut impl Write) -> IOResult {
+        self.write_html_head(out)?;
+
+        writeln!(out, "<h1 class=\"header\">{}</h1>", Escape(&self.message))?;
+        writeln!(out, "<table class=\"panic\">")?;
+        writeln!(
+            out,
+            "<tr><th>Message:</th><td class=\"msg-loc-prefix\">{}</td></tr>",
+            Escape(panic_payload(pi))
+        )?;
+        write!(out, "<tr><th>Location:</th><td>")?;
+        if let Some(loc) = pi.location() {
+            write!(
+                out,
+                "<span class=\"src-loc\">{}</span>\
+                 <span class=\"src-loc-separator\">:</span>\
+                 <span class=\"src-loc\">{}</span>",
+                Escape(loc.file()),
+                loc.line()
+            )?;
+        } else {
+            write!(out, "&lt;unknown&gt;")?;
+        }
+        writeln!(out, "</td></tr>")?;
+        writeln!(out, "</table>")?;
+
+        if self.current_verbosity() >= Verbosity::Medium {
+            self.write_trace_html(&backtrace::Backtrace::new(), out)?;
+        }
+
+        write_html_tail(out)
+    }
+
+    fn write_html_head(&self, out: &mut impl Write) -> IOResult {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html>")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title>", Escape(&self.message))?;
+        writeln!(out, "<style>")?;
+        write_style_sheet(out, &self.colors)?;
+        writeln!(out, "</style>")?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")
+    }
+
+    fn write_trace_html(&self, trace: &backtrace::Backtrace, out: &mut impl Write) -> IOResult {
+        self.render_trace(
+            trace,
+            &mut HtmlRenderer {
+                printer: self,
+                out,
+                in_source: false,
+            },
+        )
+    }
+}
+
+/// Renders the backtrace section of the HTML document.
+struct HtmlRenderer<'a, W> {
+    printer: &'a BacktracePrinter,
+    out: &'a mut W,
+    in_source: bool,
+}
+
+impl<W: Write> HtmlRenderer<'_, W> {
+    fn close_source(&mut self) -> IOResult {
+        if self.in_source {
+            self.in_source = false;
+            writeln!(self.out, "</pre>")?;
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> IOResult {
+        let out = &mut *self.out;
+        let class = if self.printer.is_dependency_frame(frame) {
+            "dependency-code"
+        } else {
+            "crate-code"
+        };
+
+        writeln!(out, "<div class=\"frame\">")?;
+        write!(out, "<div class=\"frame-name\">{:>2}: ", frame.n)?;
+        if self.printer.should_print_addresses() {
+            if let Some((module_name, module_base)) = frame.module_info() {
+                write!(
+                    out,
+                    "{}:0x{:08x} - ",
+                    Escape(&module_name),
+                    frame.ip - module_base
+                )?;
+            } else {
+                write!(out, "0x{:016x} - ", frame.ip)?;
+            }
+        }
+
+        let (name, hash) = frame.name_and_hash();
+        write!(out, "<span class=\"{}\">{}</span>", class, Escape(name))?;
+        if let Some(hash) = hash.filter(|_| !self.printer.strip_function_hash) {
+            write!(
+                out,
+                "<span class=\"{}-hash\">{}</span>",
+                class,
+                Escape(hash)
+            )?;
+        }
+        if let Some(badge) = frame
+            .crate_badge()
+            .filter(|_| self.printer.should_print_crate_badges)
+        {
+            write!(
+                out,
+                " <span class=\"crate-badge\">{}</span>",
+                Escape(&badge)
+            )?;
+        }
+        writeln!(out, "</div>")?;
+
+        match (&frame.filename, frame.lineno) {
+            (Some(file), lineno) => {
+                let lineno = lineno.map_or("&lt;unknown line&gt;".to_owned(), |x| x.to_string());
+                let location = format!("{}:{}", Escape(&self.printer.display_path(file)), lineno);
+                match self.printer.permalink(frame) {
+                    Some(url) => writeln!(
+                        out,
+                        "<div class=\"frame-location\">at <a class=\"permalink\" href=\"{}\">{}</a></div>",
+                        Escape(&url),
+                        location
+                    )?,
+                    None => writeln!(out, "<div class=\"frame-location\">at {}</div>", location)?,
+                }
+            }
+            (None, _) => writeln!(
+                out,
+                "<div class=\"frame-location\">at &lt;unknown source file&gt;</div>"
+            )?,
+        }
+
+        writeln!(out, "</div>")
+    }
+}
+
+impl<W: Write> TraceRenderer for HtmlRenderer<'_, W> {
+    fn header(&mut self, _frames: &[Frame], _filtered_frames: &[&Frame]) -> IOResult {
+        writeln!(self.out, "<h2>Backtrace</h2>")?;
+        writeln!(self.out, "<div class=\"backtrace\">")
+    }
+
+    fn hidden_frames(&mut self, frames: &[Frame]) -> IOResult {
+        self.close_source()?;
+        writeln!(self.out, "<details class=\"frames-omitted\">")?;
+        writeln!(
+            self.out,
+            "<summary class=\"frames-omitted-msg\">⋮ {} frame{} hidden ⋮</summary>",
+            frames.len(),
+            if frames.len() == 1 { "" } else { "s" },
+        )?;
+        for frame in frames {
+            self.write_frame(frame)?;
+        }
+        writeln!(self.out, "</details>")
+    }
+
+    fn repeated_frames(&mut self, frames: &[&Frame], period: usize) -> IOResult {
+        self.close_source()?;
+        writeln!(self.out, "<details class=\"frames-omitted\">")?;
+        writeln!(
+            self.out,
+            "<summary class=\"frames-omitted-msg\">{}</summary>",
+            repetition_msg(frames.len(), period),
+        )?;
+        for frame in frames {
+            self.write_frame(frame)?;
+        }
+        writeln!(self.out, "</details>")
+    }
+
+    fn collapsed_frames(&mut self, frames: &[&Frame], crate_name: &str) -> IOResult {
+        self.close_source()?;
+        writeln!(self.out, "<details class=\"frames-omitted\">")?;
+        writeln!(
+            self.out,
+            "<summary class=\"frames-omitted-msg\">⋮ {} frames in {} ⋮</summary>",
+            frames.len(),
+            Escape(crate_name),
+        )?;
+        for frame in frames {
+            self.write_frame(frame)?;
+        }
+        writeln!(self.out, "</details>")
+    }
+
+    fn frame(&mut self, frame: &Frame) -> IOResult {
+        self.close_source()?;
+        self.write_frame(frame)
+    }
+
+    fn source_line(&mut self, frame: &Frame, lineno: u32, line: &str) -> IOResult {
+        if !self.in_source {
+            self.in_source = true;
+            if self.printer.is_dependency_frame(frame) {
+                writeln!(self.out, "<pre class=\"source dependency-src-ln\">")?;
+            } else {
+                writeln!(self.out, "<pre class=\"source\">")?;
+            }
+        }
+
+        if Some(lineno) == frame.lineno {
+            writeln!(
+                self.out,
+                "<span class=\"selected-src-ln\">{:>8} &gt; {}</span>",
+                lineno,
+                Escape(line)
+            )?;
+            match frame.column_marker(line, 11) {
+                Some(marker) if self.printer.should_mark_column => writeln!(
+                    self.out,
+                    "<span class=\"selected-src-ln\">{}</span>",
+                    marker
+                ),
+                _ => Ok(()),
+            }
+        } else {
+            writeln!(self.out, "{:>8} │ {}", lineno, Escape(line))
+        }
+    }
+
+    fn empty(&mut self) -> IOResult {
+        writeln!(self.out, "<p>&lt;empty backtrace&gt;</p>")
+    }
+
+    fn footer(&mut self) -> IOResult {
+        self.close_source()?;
+        writeln!(self.out, "</div>")
+    }
+}
+
+fn write_html_tail(out: &mut impl Write) -> IOResult {
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_style_sheet(out: &mut impl Write, colors: &ColorScheme) -> IOResult {
+    writeln!(
+        out,
+        "body {{ background: #1e1e1e; color: #e5e5e5; font-family: monospace; }}"
+    )?;
+    writeln!(out, "th {{ text-align: left; font-weight: normal; }}")?;
+    writeln!(out, ".frame {{ margin: 0.2em 0; }}")?;
+    writeln!(out, ".frame-name, .frame-location {{ white-space: pre; }}")?;
+    writeln!(out, ".frame-location {{ padding-left: 4ch; }}")?;
+    writeln!(out, ".source {{ margin: 0.2em 0; }}")?;
+    writeln!(out, ".frames-omitted {{ margin: 0.2em 0; }}")?;
+    writeln!(
+        out,
+        ".frames-omitted > summary {{ text-align: center; cursor: pointer; }}"
+    )?;
+    writeln!(
+        out,
+        ".frames-omitted[open] {{ border-left: 1px dashed; padding-left: 1ch; }}"
+    )?;
+
+    let rules: &[(&str, &ColorSpec)] = &[
+        (".frames-omitted-msg", &colors.frames_omitted_msg),
+        (".header", &colors.header),
+        (".msg-loc-prefix", &colors.msg_loc_prefix),
+        (".src-loc", &colors.src_loc),
+        (".src-loc-separator", &colors.src_loc_separator),
+        (".dependency-code", &colors.dependency_code),
+        (".dependency-code-hash", &colors.dependency_code_hash),
+        (".crate-code", &colors.crate_code),
+        (".crate-code-hash", &colors.crate_code_hash),
+        (".selected-src-ln", &colors.selected_src_ln),
+        (".crate-badge", &colors.crate_badge),
+        (".dependency-src-ln", &colors.dependency_src_ln),
+        (".permalink", &colors.permalink),
+    ];
+    for (selector, spec) in rules {
+        writeln!(out, "{} {{ {}}}", selector, Css(spec))?;
+    }
+
+    Ok(())
+}
+
+/// Displays a `ColorSpec` as CSS declarations.
+struct Css<'a>(&'a ColorSpec);
+
+impl std::fmt::Display for Css<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let spec = self.0;
+        if let Some(fg) = spec.fg() {
+            write!(f, "color: {}; ", css_color(fg, spec.intense()))?;
+        }
+        if let Some(bg) = spec.bg() {
+            write!(f, "background-color: {}; ", css_color(bg, spec.intense()))?;
+        }
+        write!(
+            f,
+            "font-weight: {}; ",
+            if spec.bold() { "bold" } else { "normal" }
+        )?;
+        if spec.italic() {
+            write!(f, "font-style: italic; ")?;
+        }
+        if spec.underline() {
+            write!(f, "text-decoration: underline; ")?;
+        }
+        if spec.dimmed() {
+            write!(f, "opacity: 0.6; ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Map a terminal color to a CSS color, using the xterm palette.
+fn css_color(color: &Color, intense: bool) -> String {
+    const PALETTE: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00),
+        (0xcd, 0x00, 0x00),
+        (0x00, 0xcd, 0x00),
+        (0xcd, 0xcd, 0x00),
+        (0x00, 0x00, 0xee),
+        (0xcd, 0x00, 0xcd),
+        (0x00, 0xcd, 0xcd),
+        (0xe5, 0xe5, 0xe5),
+        (0x7f, 0x7f, 0x7f),
+        (0xff, 0x00, 0x00),
+        (0x00, 0xff, 0x00),
+        (0xff, 0xff, 0x00),
+        (0x5c, 0x5c, 0xff),
+        (0xff, 0x00, 0xff),
+        (0x00, 0xff, 0xff),
+        (0xff, 0xff, 0xff),
+    ];
+
+    let ansi = |idx: u8| -> (u8, u8, u8) {
+        match idx {
+            0..=15 => PALETTE[idx as usize],
+            16..=231 => {
+                let level = |x: u8| if x == 0 { 0 } else { 55 + x * 40 };
+                let idx = idx - 16;
+                (level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+            }
+            _ => {
+                let gray = 8 + (idx - 232) * 10;
+                (gray, gray, gray)
+            }
+        }
+    };
+
+    let offset = if intense { 8 } else { 0 };
+    let (r, g, b) = match *color {
+        Color::Black => ansi(offset),
+        Color::Red => ansi(1 + offset),
+        Color::Green => ansi(2 + offset),
+        Color::Yellow => ansi(3 + offset),
+        Color::Blue => ansi(4 + offset),
+        Color::Magenta => ansi(5 + offset),
+        Color::Cyan => ansi(6 + offset),
+        Color::White => ansi(7 + offset),
+        Color::Ansi256(idx) => ansi(idx),
+        Color::Rgb(r, g, b) => (r, g, b),
+        _ => return "inherit".to_owned(),
+    };
+
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Displays a string with HTML special characters escaped.
+struct Escape<'a>(&'a str);
+
+impl std::fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&#39;")?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+//! Machine-readable JSON output.
+//!
+//! The document produced here is built from the same frame filtering as the
+//! colored terminal output, so log pipelines can index panics without having to
+//! scrape escape codes. The output is written as a single line, making it
+//! suitable for newline-delimited JSON logs.
+//!
+//! Schema (version 1):
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "message": "The application panicked (crashed).",
+//!   "payload": "called `Option::unwrap()` on a `None` value",
+//!   "location": { "file": "src/main.rs", "line": 4, "column": 5 } | null,
+//!   "verbosity": "minimal" | "medium" | "full",
+//!   "backtrace": {
+//!     "total_frames": 42,
+//!     "frames": [
+//!       {
+//!         "n": 13,
+//!         "name": "app::main::h1234567890abcdef" | null,
+//!         "filename": "src/main.rs" | null,
+//!         "lineno": 4 | null,
+//!         "colno": 5 | null,
+//!         "ip": 94823749012,
+//!         "crate": "app" | null,
+//!         "crate_version": "1.2.3" | null,
+//!         "permalink": "https://github.com/org/app/blob/abc123/src/main.rs#L4" | null,
+//!         "is_dependency_code": false,
+//!         "is_post_panic_code": false,
+//!         "is_runtime_init_code": false,
+//!         "hidden_frames_before": 12
+//!       }
+//!     ],
+//!     "hidden_frames_after": 29
+//!   } | null
+//! }
+//! ```
+//!
+//! `print_trace_json` emits only the `backtrace` object.
+
+use crate::{
+    panic_payload, BacktracePrinter, Frame, IOResult, PanicHookInfo, TraceRenderer, Verbosity,
+};
+use std::io::Write;
+use std::sync::Mutex;
+
+const SCHEMA_VERSION: u32 = 1;
+
+/// JSON output.
+impl BacktracePrinter {
+    /// Prints a [`backtrace::Backtrace`](backtrace::Backtrace) as a JSON object.
+    pub fn print_trace_json(&self, trace: &backtrace::Backtrace, out: &mut impl Write) -> IOResult {
+        self.write_trace_json(trace, out)?;
+        writeln!(out)
+    }
+
+    /// Format a backtrace as a JSON `String`.
+    pub fn format_trace_to_json_string(&self, trace: &backtrace::Backtrace) -> IOResult<String> {
+        let mut buf = vec![];
+        self.write_trace_json(trace, &mut buf)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    /// Prints a [`PanicInfo`](std::panic::PanicInfo) struct as a JSON document.
+    ///
+    /// The backtrace is only captured if the verbosity is at least
+    /// [`Verbosity::Medium`], otherwise the `backtrace` field is `null`.
+    pub fn print_panic_info_json(&self, pi: &PanicHookInfo, out: &mut impl Write) -> IOResult {
+        write!(out, "{{\"version\":{},\"message\":", SCHEMA_VERSION)?;
+        write_str(out, &self.message)?;
+        write!(out, ",\"payload\":")?;
+        write_str(out, panic_payload(pi))?;
+
+        write!(out, ",\"location\":")?;
+        if let Some(loc) = pi.location() {
+            write!(out, "{{\"file\":")?;
+            write_str(out, loc.file())?;
+            write!(
+                out,
+                ",\"line\":{},\"column\":{}}}",
+                loc.line(),
+                loc.column()
+            )?;
+        } else {
+            write!(out, "null")?;
+        }
+
+        write!(out, ",\"verbosity\":")?;
+        write_str(out, verbosity_name(self.current_verbosity()))?;
+
+        write!(out, ",\"backtrace\":")?;
+        if self.current_verbosity() >= Verbosity::Medium {
+            self.write_trace_json(&backtrace::Backtrace::new(), out)?;
+        } else {
+            write!(out, "null")?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    /// Create a panic handler that prints panics as JSON documents.
+    ///
+    /// This is the JSON counterpart of
+    /// [`into_panic_handler`](BacktracePrinter::into_panic_handler).
+    pub fn into_json_panic_handler(
+        mut self,
+        out: impl Write + Sync + Send + 'static,
+    ) -> Box<dyn Fn(&PanicHookInfo<'_>) + 'static + Sync + Send> {
+        self.is_panic_handler = true;
+        let out_stream_mutex = Mutex::new(out);
+        Box::new(move |pi| {
+            let mut lock = out_stream_mutex.lock().unwrap();
+            if let Err(e) = self.print_panic_info_json(pi, &mut *lock) {
+                // Panicking while handling a panic would send us into a deadlock,
+                // so we just print the error to stderr instead.
+                eprintln!("Error while printing panic: {:?}", e);
+            }
+            if let Err(e) = self.write_crash_reports(pi) {
+                eprintln!("Error while writing crash report: {:?}", e);
+            }
+        })
+    }
+
+    fn write_trace_json(&self, trace: &backtrace::Backtrace, out: &mut impl Write) -> IOResult {
+        self.render_trace(trace, &mut JsonRenderer::new(self, out))
+    }
+}
+
+/// Renders the `backtrace` object of the JSON document.
+struct JsonRenderer<'a, W> {
+    printer: &'a BacktracePrinter,
+    out: &'a mut W,
+    total_frames: usize,
+    last_n: usize,
+    hidden_before: usize,
+}
+
+impl<'a, W: Write> JsonRenderer<'a, W> {
+    fn new(printer: &'a BacktracePrinter, out: &'a mut W) -> Self {
+        Self {
+            printer,
+            out,
+            total_frames: 0,
+            last_n: 0,
+            hidden_before: 0,
+        }
+    }
+}
+
+impl<W: Write> TraceRenderer for JsonRenderer<'_, W> {
+    fn header(&mut self, frames: &[Frame], _filtered_frames: &[&Frame]) -> IOResult {
+        self.total_frames = frames.len();
+        write!(self.out, "{{\"total_frames\":{},\"frames\":[", frames.len())
+    }
+
+    fn hidden_frames(&mut self, frames: &[Frame]) -> IOResult {
+        self.hidden_before = frames.len();
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &Frame) -> IOResult {
+        if self.last_n != 0 {
+            write!(self.out, ",")?;
+        }
+        write_frame(
+            self.out,
+            frame,
+            self.printer.permalink(frame).as_deref(),
+            self.printer.is_dependency_frame(frame),
+            self.hidden_before,
+        )?;
+        self.hidden_before = 0;
+        self.last_n = frame.n;
+        Ok(())
+    }
+
+    fn footer(&mut self) -> IOResult {
+        write!(
+            self.out,
+            "],\"hidden_frames_after\":{}}}",
+            self.total_frames - self.last_n
+        )
+    }
+}
+
+fn write_frame(
+    out: &mut impl Write,
+    frame: &Frame,
+    permalink: Option<&str>,
+    is_dependency_code: bool,
+    hidden_before: usize,
+) -> IOResult {
+    write!(out, "{{\"n\":{},\"name\":", frame.n)?;
+    write_opt_str(out, frame.name.as_deref())?;
+    write!(out, ",\"filename\":")?;
+    write_opt_str(
+        out,
+        frame
+            .filename
+            .as_ref()
+            .map(|x| x.to_string_lossy())
+            .as_deref(),
+    )?;
+    write!(out, ",\"lineno\":")?;
+    match frame.lineno {
+        Some(lineno) => write!(out, "{}", lineno)?,
+        None => write!(out, "null")?,
+    }
+    write!(out, ",\"colno\":")?;
+    match frame.colno {
+        Some(colno) => write!(out, "{}", colno)?,
+        None => write!(out, "null")?,
+    }
+    write!(out, ",\"ip\":{},\"crate\":", frame.ip)?;
+    write_opt_str(out, frame.crate_name().as_deref())?;
+    write!(out, ",\"crate_version\":")?;
+    write_opt_str(out, frame.crate_version())?;
+    write!(out, ",\"permalink\":")?;
+    write_opt_str(out, permalink)?;
+    write!(
+        out,
+        ",\"is_dependency_code\":{},\"is_post_panic_code\":{},\
+         \"is_runtime_init_code\":{},\"hidden_frames_before\":{}}}",
+        is_dependency_code,
+        frame.is_post_panic_code(),
+        frame.is_runtime_init_code(),
+        hidden_before,
+    )
+}
+
+fn verbosity_name(v: Verbosity) -> &'static str {
+    match v {
+        Verbosity::Minimal => "minimal",
+        Verbosity::Medium => "medium",
+        Verbosity::Full => "full",
+    }
+}
+
+fn write_opt_str(out: &mut impl Write, s: Option<&str>) -> IOResult {
+    match s {
+        Some(s) => write_str(out, s),
+        None => write!(out, "null"),
+    }
+}
+
+/// Write a JSON string literal, escaping as required by RFC 8259.
+fn write_str(out: &mut impl Write, s: &str) -> IOResult {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn frame(n: usize, name: Option<&str>, filename: Option<&str>) -> Frame {
+        Frame {
+            n,
+            name: name.map(Into::into),
+            lineno: filename.map(|_| 4),
+            colno: filename.map(|_| 5),
+            filename: filename.map(PathBuf::from),
+            ip: n * 16,
+        }
+    }
+
+    /// Render `frames`, keeping those whose number is in `visible`.
+    fn render(frames: Vec<Frame>, visible: &'static [usize]) -> String {
+        let printer = BacktracePrinter::new()
+            .verbosity(Verbosity::Medium)
+            .lib_verbosity(Verbosity::Medium)
+            .clear_frame_filters()
+            .add_frame_filter(Box::new(|frames| frames.retain(|x| visible.contains(&x.n))));
+        let mut out = vec![];
+        printer
+            .render_frames(frames, &mut JsonRenderer::new(&printer, &mut out))
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let mut out = vec![];
+        write_str(&mut out, "\"a\\b\"\n\r\t\u{1}\u{1f} ü…").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#""\"a\\b\"\n\r\t\u0001\u001f ü…""#
+        );
+    }
+
+    #[test]
+    fn frames_and_hidden_counts() {
+        let frames = vec![
+            frame(1, Some("std::panicking::begin_panic"), None),
+            frame(2, Some("app::main"), Some(r"C:\app\src\main.rs")),
+            frame(3, None, None),
+            frame(4, Some("std::rt::lang_start"), None),
+            frame(5, Some("main"), None),
+        ];
+        assert_eq!(
+            render(frames, &[2, 3]),
+            concat!(
+                r#"{"total_frames":5,"frames":["#,
+                r#"{"n":2,"name":"app::main","filename":"C:\\app\\src\\main.rs","lineno":4,"#,
+                r#""colno":5,"ip":32,"crate":"app","crate_version":null,"permalink":null,"#,
+                r#""is_dependency_code":false,"is_post_panic_code":false,"#,
+                r#""is_runtime_init_code":false,"hidden_frames_before":1},"#,
+                r#"{"n":3,"name":null,"filename":null,"lineno":null,"colno":null,"ip":48,"#,
+                r#""crate":null,"crate_version":null,"permalink":null,"#,
+                r#""is_dependency_code":false,"is_post_panic_code":false,"#,
+                r#""is_runtime_init_code":false,"hidden_frames_before":0}"#,
+                r#"],"hidden_frames_after":2}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn empty_backtrace() {
+        assert_eq!(
+            render(vec![], &[]),
+            r#"{"total_frames":0,"frames":[],"hidden_frames_after":0}"#
+        );
+        let frames = vec![frame(1, Some("app::main"), None)];
+        assert_eq!(
+            render(frames, &[]),
+            r#"{"total_frames":1,"frames":[],"hidden_frames_after":1}"#
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Adapting the terminal output to its width.
+//!
+//! Monomorphized function names can be hundreds of characters long. Rather
+//! than leaving them to be wrapped by the terminal mid-identifier, generic
+//! arguments are elided from the innermost out until a name fits, and names
+//! that still don't fit are wrapped at path separators.
+//!
+//! Output that isn't laid out for a width, like strings and report files, is
+//! left alone: it's likely read in an editor or searched for names.
+
+use std::borrow::Cow;
+
+/// Width of banners if the output isn't laid out for a terminal.
+pub(crate) const DEFAULT_WIDTH: usize = 80;
+
+/// Narrower widths are raised to this, there's no sensible layout below.
+pub(crate) const MIN_WIDTH: usize = 40;
+
+/// Determine the width of the terminal connected to stderr, where backtraces
+/// are printed by default.
+#[cfg(unix)]
+pub(crate) fn terminal_width() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let res = unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (res == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn terminal_width() -> Option<usize> {
+    None
+}
+
+/// Shorten a demangled name to at most `width` characters by eliding generic
+/// arguments, innermost first.
+///
+/// Only the arguments of paths (`Vec<…>`, `collect::<…>`) are elided. The
+/// `<T as Trait>` of qualified paths is kept, as it identifies the method.
+/// The result may still be longer than `width`.
+pub(crate) fn elide_generics(name: &str, width: usize) -> Cow<'_, str> {
+    if name.chars().count() <= width {
+        return Cow::Borrowed(name);
+    }
+
+    let groups = generic_args(name);
+    let max_depth = groups.iter().map(|x| x.2).max().unwrap_or(0);
+
+    let mut elided = Cow::Borrowed(name);
+    for depth in (1..=max_depth).rev() {
+        let mut out = String::with_capacity(name.len());
+        let mut pos = 0;
+        for &(start, end, _) in groups.iter().filter(|x| x.2 >= depth) {
+            // Groups nested in an already elided one are skipped.
+            if start >= pos {
+                out += &name[pos..start];
+                out.push('…');
+                pos = end;
+            }
+        }
+        out += &name[pos..];
+
+        elided = Cow::Owned(out);
+        if elided.chars().count() <= width {
+            break;
+        }
+    }
+    elided
+}
+
+/// Byte ranges of the contents of all non-empty generic argument lists in a
+/// name, ordered by start, along with their nesting depth.
+fn generic_args(name: &str) -> Vec<(usize, usize, usize)> {
+    let mut groups = vec![];
+    // Start of the contents of the open `<`, and whether it opens arguments.
+    let mut open: Vec<(usize, bool)> = vec![];
+    let mut prev = None;
+    for (i, c) in name.char_indices() {
+        match c {
+            '<' => {
+                let is_args =
+                    prev.is_some_and(|p: char| p.is_alphanumeric() || p == '_' || p == ':');
+                open.push((i + 1, is_args));
+            }
+            // Not the arrow of `fn() -> T`.
+            '>' if prev != Some('-') => {
+                if let Some((start, is_args)) = open.pop() {
+                    if is_args && start < i {
+                        groups.push((start, i, open.len() + 1));
+                    }
+                }
+            }
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    groups.sort_unstable();
+    groups
+}
+
+/// Split a name into lines of at most `first_width` characters for the first
+/// and `width` for the following lines, breaking before path separators or
+/// after commas.
+///
+/// Identifiers are never split, so lines may exceed the width if there is no
+/// place to break.
+pub(crate) fn wrap_name(name: &str, first_width: usize, width: usize) -> Vec<&str> {
+    let mut lines = vec![];
+    let mut rest = name;
+    let mut line_width = first_width;
+    while rest.chars().count() > line_width {
+        let limit = rest
+            .char_indices()
+            .nth(line_width)
+            .map_or(rest.len(), |x| x.0);
+        let breaks = rest
+            .match_indices("::")
+            .map(|x| x.0)
+            .chain(rest.match_indices(", ").map(|x| x.0 + 2))
+            .filter(|&i| i > 0);
+        // Break at the last opportunity that fits, or the first after it.
+        let split = match breaks.clone().filter(|&i| i <= limit).max() {
+            Some(split) => split,
+            None => match breaks.min() {
+                Some(split) => split,
+                None => break,
+            },
+        };
+        lines.push(&rest[..split]);
+        rest = &rest[split..];
+        line_width = width;
+    }
+    lines.push(rest);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elide_generics_innermost_first() {
+        let name = "alloc::vec::Vec<core::option::Option<alloc::string::String>>::push";
+        assert!(matches!(elide_generics(name, 100), Cow::Borrowed(x) if x == name));
+        assert_eq!(
+            elide_generics(name, 50),
+            "alloc::vec::Vec<core::option::Option<…>>::push"
+        );
+        assert_eq!(elide_generics(name, 40), "alloc::vec::Vec<…>::push");
+    }
+
+    #[test]
+    fn elide_generics_skips_fn_arrows() {
+        let name = "core::ops::function::FnOnce<fn() -> alloc::vec::Vec<u8>>::call_once";
+        assert_eq!(
+            elide_generics(name, 66),
+            "core::ops::function::FnOnce<fn() -> alloc::vec::Vec<…>>::call_once"
+        );
+        assert_eq!(
+            elide_generics(name, 10),
+            "core::ops::function::FnOnce<…>::call_once"
+        );
+    }
+
+    #[test]
+    fn elide_generics_keeps_qualified_paths() {
+        let name = "<alloc::vec::Vec<u8> as core::clone::Clone>::clone";
+        assert_eq!(
+            elide_generics(name, 10),
+            "<alloc::vec::Vec<…> as core::clone::Clone>::clone"
+        );
+        assert_eq!(elide_generics("<T as Trait>::f", 5), "<T as Trait>::f");
+    }
+
+    #[test]
+    fn wrap_name_at_separators() {
+        assert_eq!(
+            wrap_name("std::panicking::begin_panic_handler", 20, 20),
+            vec!["std::panicking", "::begin_panic_handler"]
+        );
+        assert_eq!(
+            wrap_name("core::result::Result<u8, alloc::string::String>", 26, 26),
+            vec!["core::result::Result<u8, ", "alloc::string::String>"]
+        );
+        assert_eq!(
+            wrap_name("std::rt::lang_start", 40, 40),
+            vec!["std::rt::lang_start"]
+        );
+    }
+
+    #[test]
+    fn wrap_name_never_splits_identifiers() {
+        let name = "an_identifier_much_longer_than_the_line";
+        assert_eq!(wrap_name(name, 10, 10), vec![name]);
+        assert_eq!(
+            wrap_name("a_long_module_name::another_long_name", 10, 10),
+            vec!["a_long_module_name", "::another_long_name"]
+        );
+    }
+}
@@ -15,6 +15,9 @@
 //! - Print frames of application code vs dependencies in different color
 //! - Hide all the frames after the panic was already initiated
 //! - Hide language runtime initialization frames
+//! - Emit panics as JSON documents for log pipelines
+//! - Render self-contained HTML crash reports
+//! - Render Markdown reports for issue trackers
 //!
 //! ### Installing the panic handler
 //!
@@ -35,24 +38,59 @@
 //! [minimal](Verbosity::Minimal), `RUST_BACKTRACE=1` to
 //! [medium](Verbosity::Medium) and `RUST_BACKTRACE=full` to
 //! [full](Verbosity::Full) verbosity levels.
+//!
+//! ### Filtering frames
+//! Frames are filtered by callbacks added via
+//! [`add_frame_filter`](BacktracePrinter::add_frame_filter) and by
+//! [`FilterRule`]s, which can also be supplied at runtime via the
+//! `COLORBT_FILTER` and `COLORBT_FILTER_FILE` environment variables:
+//! ```text
+//! COLORBT_FILTER='hide crate:tokio; show name:*parse_*' ./my-app
+//! ```
+//! Setting `COLORBT_SHOW_HIDDEN=1` disables all filtering.
 
+use highlight::{Highlighter, Token};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader, ErrorKind, IsTerminal as _};
-use std::panic::PanicInfo;
-use std::path::PathBuf;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use termcolor::{Ansi, Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use termcolor::{Ansi, Color, ColorChoice, ColorSpec, HyperlinkSpec, StandardStream, WriteColor};
 
 // Re-export termcolor so users don't have to depend on it themselves.
 pub use termcolor;
 
+pub use report::CrashReportConfig;
+pub use rules::{FilterPreset, FilterRule};
+#[cfg(all(unix, feature = "signal-handler"))]
+pub use signal::install_signal_stack;
+
+mod highlight;
+mod html;
+mod json;
+mod layout;
+mod markdown;
+mod report;
+mod rules;
+#[cfg(all(unix, feature = "signal-handler"))]
+mod signal;
+mod std_src;
+
 // ============================================================================================== //
 // [Result / Error types]                                                                         //
 // ============================================================================================== //
 
 type IOResult<T = ()> = Result<T, std::io::Error>;
 
+/// `std::panic::PanicHookInfo` only exists since Rust 1.81, so refer to it by
+/// its deprecated former name to keep supporting older compilers.
+#[allow(deprecated)]
+type PanicHookInfo<'a> = std::panic::PanicInfo<'a>;
+
 // ============================================================================================== //
 // [Verbosity management]                                                                         //
 // ============================================================================================== //
@@ -121,13 +159,113 @@ pub fn default_output_stream() -> Box<StandardStream> {
     }))
 }
 
+/// Determines what happens to the previously installed panic hook when a
+/// `BacktracePrinter` is [installed](BacktracePrinter::install).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviousHook {
+    /// Replace the previous hook, it is never called again.
+    Discard,
+    /// Keep the previous hook and call it before printing the panic.
+    CallBefore,
+    /// Keep the previous hook and call it after printing the panic.
+    CallAfter,
+}
+
+type PanicHook = dyn Fn(&PanicHookInfo<'_>) + 'static + Sync + Send;
+
+/// Hooks replaced by chaining `install` calls, most recent last.
+static PREVIOUS_HOOKS: Mutex<Vec<Arc<PanicHook>>> = Mutex::new(Vec::new());
+
+/// Restore the panic hook that was active before the most recent `install`
+/// with [`PreviousHook::CallBefore`] or [`PreviousHook::CallAfter`].
+///
+/// Returns `false` if there is no such hook. Hooks set by other means
+/// in the meantime are overwritten.
+pub fn restore_previous_hook() -> bool {
+    match PREVIOUS_HOOKS.lock().unwrap().pop() {
+        Some(previous) => {
+            std::panic::set_hook(Box::new(move |pi| previous(pi)));
+            true
+        }
+        None => false,
+    }
+}
+
+thread_local! {
+    /// Handlers of the live `PanicHookGuard`s created on this thread, along
+    /// with the id of their guard, innermost last.
+    static SCOPED_HANDLERS: RefCell<Vec<(usize, Arc<PanicHook>, PreviousHook)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Source of the ids identifying the handlers of `PanicHookGuard`s.
+static NEXT_GUARD_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Bookkeeping for the process-wide hook that dispatches to scoped handlers.
+struct ScopedDispatch {
+    /// Number of live `PanicHookGuard`s across all threads.
+    guards: usize,
+    /// The hook replaced by the dispatcher, while the dispatcher is installed.
+    previous: Option<Arc<PanicHook>>,
+}
+
+static SCOPED_DISPATCH: Mutex<ScopedDispatch> = Mutex::new(ScopedDispatch {
+    guards: 0,
+    previous: None,
+});
+
+/// Guard returned by [`BacktracePrinter::install_scoped`].
+///
+/// While the guard is alive, panics on the thread that created it are printed
+/// by its `BacktracePrinter`, or that of the most recently created guard still
+/// alive. Dropping it removes its handler, even if other guards were created
+/// after it.
+#[must_use = "the handler is uninstalled when the guard is dropped"]
+pub struct PanicHookGuard {
+    id: usize,
+    // The handler is registered for the creating thread only.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        SCOPED_HANDLERS.with(|handlers| handlers.borrow_mut().retain(|x| x.0 != self.id));
+
+        let mut dispatch = SCOPED_DISPATCH.lock().unwrap_or_else(|e| e.into_inner());
+        dispatch.guards -= 1;
+
+        // `set_hook` panics on a panicking thread. In that case the dispatcher
+        // stays in place, which behaves just like the previous hook.
+        if dispatch.guards == 0 && !std::thread::panicking() {
+            if let Some(previous) = dispatch.previous.take() {
+                std::panic::set_hook(Box::new(move |pi| previous(pi)));
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for PanicHookGuard {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("PanicHookGuard").finish()
+    }
+}
+
+/// Extract the panic message from the payload, if it is a string.
+fn panic_payload<'a>(pi: &'a PanicHookInfo) -> &'a str {
+    pi.payload()
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| pi.payload().downcast_ref::<&str>().cloned())
+        .unwrap_or("<non string panic payload>")
+}
+
 #[deprecated(
     since = "0.4.0",
     note = "Use `BacktracePrinter::into_panic_handler()` instead."
 )]
 pub fn create_panic_handler(
     printer: BacktracePrinter,
-) -> Box<dyn Fn(&PanicInfo<'_>) + 'static + Sync + Send> {
+) -> Box<dyn Fn(&PanicHookInfo<'_>) + 'static + Sync + Send> {
     let out_stream_mutex = Mutex::new(default_output_stream());
     Box::new(move |pi| {
         let mut lock = out_stream_mutex.lock().unwrap();
@@ -151,21 +289,24 @@ pub fn install_with_settings(printer: BacktracePrinter) {
 pub type FilterCallback = dyn Fn(&mut Vec<&Frame>) + Send + Sync + 'static;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct Frame {
     pub n: usize,
     pub name: Option<String>,
     pub lineno: Option<u32>,
+    pub colno: Option<u32>,
     pub filename: Option<PathBuf>,
     pub ip: usize,
-    _private_ctor: (),
 }
 
 impl Frame {
     /// Heuristically determine whether the frame is likely to be part of a
     /// dependency.
     ///
-    /// If it fails to detect some patterns in your code base, feel free to drop
-    /// an issue / a pull request!
+    /// This is the fallback used by
+    /// [`BacktracePrinter::is_dependency_frame`] when no crate names or paths
+    /// were configured. If it fails to detect some patterns in your code base,
+    /// configure those instead, or feel free to drop an issue / a pull request!
     pub fn is_dependency_code(&self) -> bool {
         const SYM_PREFIXES: &[&str] = &[
             "std::",
@@ -187,6 +328,7 @@ impl Frame {
 
         // Inspect name.
         if let Some(ref name) = self.name {
+            let name = strip_disambiguators(name);
             if SYM_PREFIXES.iter().any(|x| name.starts_with(x)) {
                 return true;
             }
@@ -204,6 +346,7 @@ impl Frame {
             let filename = filename.to_string_lossy();
             if FILE_PREFIXES.iter().any(|x| filename.starts_with(x))
                 || filename.contains("/.cargo/registry/src/")
+                || filename.contains("/.cargo/git/checkouts/")
             {
                 return true;
             }
@@ -229,9 +372,14 @@ impl Frame {
             "std::panicking::begin_panic",
             "begin_panic_fmt",
             "backtrace::capture",
+            "std::backtrace_rs::",
+            "std::backtrace::Backtrace::",
+            "<std::backtrace::Backtrace>::",
+            "color_backtrace::signal::handle_signal",
+            "__restore_rt",
         ];
 
-        match self.name.as_ref() {
+        match self.name.as_deref().map(strip_disambiguators) {
             Some(name) => SYM_PREFIXES.iter().any(|x| name.starts_with(x)),
             None => false,
         }
@@ -246,8 +394,10 @@ impl Frame {
             "std::sys_common::backtrace::__rust_begin_short_backtrace",
         ];
 
-        let (name, file) = match (self.name.as_ref(), self.filename.as_ref()) {
-            (Some(name), Some(filename)) => (name, filename.to_string_lossy()),
+        let (name, file) = match (self.name.as_deref(), self.filename.as_ref()) {
+            (Some(name), Some(filename)) => {
+                (strip_disambiguators(name), filename.to_string_lossy())
+            }
             _ => return false,
         };
 
@@ -263,35 +413,164 @@ impl Frame {
         false
     }
 
-    fn print_source_if_avail(&self, mut out: impl WriteColor, s: &BacktracePrinter) -> IOResult {
-        let (lineno, filename) = match (self.lineno, self.filename.as_ref()) {
-            (Some(a), Some(b)) => (a, b),
-            // Without a line number and file name, we can't sensibly proceed.
-            _ => return Ok(()),
+    /// The name of the crate the frame belongs to.
+    ///
+    /// Frames located in a package from a cargo registry are attributed to
+    /// that package, all others to the crate their symbol name starts with.
+    /// Dashes are replaced by underscores, i.e. this is the name used in paths.
+    pub fn crate_name(&self) -> Option<Cow<'_, str>> {
+        match self.registry_package() {
+            Some((name, _)) if name.contains('-') => Some(name.replace('-', "_").into()),
+            Some((name, _)) => Some(name.into()),
+            None => self.symbol_crate().map(Cow::Borrowed),
+        }
+    }
+
+    /// The version of the crate the frame belongs to.
+    ///
+    /// This is only known for frames located in a package from a cargo
+    /// registry.
+    pub fn crate_version(&self) -> Option<&str> {
+        self.registry_package().map(|x| x.1)
+    }
+
+    /// The `[crate version]` label shown next to the frame with
+    /// [`BacktracePrinter::crate_badges`] enabled.
+    fn crate_badge(&self) -> Option<String> {
+        let name = self.crate_name()?;
+        Some(match self.crate_version() {
+            Some(version) => format!("[{} {}]", name, version),
+            None => format!("[{}]", name),
+        })
+    }
+
+    /// Split the package directory of a file in a cargo registry,
+    /// `$CARGO_HOME/registry/src/<index>/<name>-<version>/`, into name and
+    /// version.
+    fn registry_package(&self) -> Option<(&str, &str)> {
+        let components = self
+            .filename
+            .as_ref()?
+            .components()
+            .map(|x| x.as_os_str().to_str().unwrap_or(""))
+            .collect::<Vec<_>>();
+        let i = components
+            .windows(2)
+            .position(|x| x == ["registry", "src"])?;
+        let package = components.get(i + 3)?;
+
+        // Both names and pre-release versions may contain dashes, so look for
+        // the first one followed by something resembling `major.minor.patch`.
+        package
+            .match_indices('-')
+            .map(|(j, _)| (&package[..j], &package[j + 1..]))
+            .find(|(_, version)| {
+                let mut parts = version.splitn(3, '.');
+                let is_num = |x: &str| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit());
+                parts.next().is_some_and(is_num)
+                    && parts.next().is_some_and(is_num)
+                    && parts
+                        .next()
+                        .is_some_and(|x| x.starts_with(|c: char| c.is_ascii_digit()))
+            })
+    }
+
+    /// The name of the crate the function belongs to, as far as it can be told
+    /// from the symbol name.
+    ///
+    /// Trait implementations are attributed to the crate of the implementing
+    /// type and the disambiguators of v0 mangled names are stripped.
+    fn symbol_crate(&self) -> Option<&str> {
+        let mut name = self.name.as_deref()?;
+
+        // `<&mut foo::Bar as core::fmt::Debug>::fmt` belongs to `foo`.
+        loop {
+            let trimmed = name.trim_start_matches(['<', '&', '*']);
+            let trimmed = ["mut ", "const ", "dyn "]
+                .iter()
+                .fold(trimmed, |x, prefix| x.strip_prefix(prefix).unwrap_or(x));
+            if trimmed.len() == name.len() {
+                break;
+            }
+            name = trimmed;
+        }
+
+        // `std[e28293b1aa0f68bd]::rt::lang_start` belongs to `std`.
+        let krate = name[..name.find("::")?].split('[').next()?;
+        if krate.is_empty() || !krate.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+
+        Some(krate)
+    }
+
+    /// Read the source lines surrounding the frame's location from `filename`,
+    /// including `before` lines before and `after` lines after it.
+    ///
+    /// Returns `(line number, line)` pairs, or an empty list if the line is
+    /// unknown or the file doesn't exist.
+    fn source_snippet(
+        &self,
+        filename: &Path,
+        before: u32,
+        after: u32,
+    ) -> IOResult<Vec<(u32, String)>> {
+        let lineno = match self.lineno {
+            Some(lineno) if lineno > 0 => lineno,
+            // Without a line number, we can't sensibly proceed.
+            _ => return Ok(vec![]),
         };
 
         let file = match File::open(filename) {
             Ok(file) => file,
-            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(()),
-            e @ Err(_) => e?,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
         };
 
-        // Extract relevant lines.
+        // Extract relevant lines. The context may be as large as `u32::MAX`.
         let reader = BufReader::new(file);
-        let start_line = lineno - 2.min(lineno - 1);
-        let surrounding_src = reader.lines().skip(start_line as usize - 1).take(5);
-        for (line, cur_line_no) in surrounding_src.zip(start_line..) {
-            if cur_line_no == lineno {
-                // Print actual source line with brighter color.
-                out.set_color(&s.colors.selected_src_ln)?;
-                writeln!(out, "{:>8} > {}", cur_line_no, line?)?;
-                out.reset()?;
-            } else {
-                writeln!(out, "{:>8} │ {}", cur_line_no, line?)?;
-            }
-        }
+        let start_line = lineno - before.min(lineno - 1);
+        let surrounding_src = reader
+            .lines()
+            .skip(start_line as usize - 1)
+            .take(((lineno - start_line) as usize + 1).saturating_add(after as usize));
+        surrounding_src
+            .zip(start_line..)
+            .map(|(line, cur_line_no)| Ok((cur_line_no, line?)))
+            .collect()
+    }
 
-        Ok(())
+    /// A line placing a caret below the frame's column in `line`, the source
+    /// line the frame is located at, with `indent` columns of space in front.
+    fn column_marker(&self, line: &str, indent: usize) -> Option<String> {
+        let colno = self.colno.filter(|&x| x > 0)? as usize;
+
+        // Keep tabs, so the caret lines up regardless of the tab width.
+        let pad = line
+            .chars()
+            .take(colno - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        Some(format!("{:indent$}{}^", "", pad, indent = indent))
+    }
+
+    /// Split the function name into the path and its `::h<hash>` suffix, if any.
+    fn name_and_hash(&self) -> (&str, Option<&str>) {
+        // Does the function have a hash suffix?
+        // (dodging a dep on the regex crate here)
+        let name = self.name.as_deref().unwrap_or("<unknown>");
+        let has_hash_suffix = name.len() > 19
+            && &name[name.len() - 19..name.len() - 16] == "::h"
+            && name[name.len() - 16..]
+                .chars()
+                .all(|x| x.is_ascii_hexdigit());
+
+        if has_hash_suffix {
+            let (name, hash) = name.split_at(name.len() - 19);
+            (name, Some(hash))
+        } else {
+            (name, None)
+        }
     }
 
     /// Get the module's name by walking /proc/self/maps
@@ -335,11 +614,9 @@ impl Frame {
                     caps.name("path").unwrap().as_str().to_string(),
                 );
                 if self.ip >= start && self.ip < end {
-                    return if let Some(filename) = Path::new(&path).file_name() {
-                        Some((filename.to_str().unwrap().to_string(), start))
-                    } else {
-                        None
-                    };
+                    return Path::new(&path)
+                        .file_name()
+                        .map(|filename| (filename.to_str().unwrap().to_string(), start));
                 }
             }
         }
@@ -356,30 +633,50 @@ impl Frame {
         None
     }
 
-    fn print(&self, i: usize, out: &mut impl WriteColor, s: &BacktracePrinter) -> IOResult {
-        let is_dependency_code = self.is_dependency_code();
+    fn print(
+        &self,
+        i: usize,
+        out: &mut impl WriteColor,
+        s: &BacktracePrinter,
+        width: Option<usize>,
+    ) -> IOResult {
+        let is_dependency_code = s.is_dependency_frame(self);
 
         // Print frame index.
-        write!(out, "{:>2}: ", i)?;
+        let mut prefix = format!("{:>2}: ", i);
 
         if s.should_print_addresses() {
             if let Some((module_name, module_base)) = self.module_info() {
-                write!(out, "{}:0x{:08x} - ", module_name, self.ip - module_base)?;
+                prefix += &format!("{}:0x{:08x} - ", module_name, self.ip - module_base);
             } else {
-                write!(out, "0x{:016x} - ", self.ip)?;
+                prefix += &format!("0x{:016x} - ", self.ip);
             }
         }
-
-        // Does the function have a hash suffix?
-        // (dodging a dep on the regex crate here)
-        let name = self
-            .name
-            .as_ref()
-            .map(|s| s.as_str())
-            .unwrap_or("<unknown>");
-        let has_hash_suffix = name.len() > 19
-            && &name[name.len() - 19..name.len() - 16] == "::h"
-            && name[name.len() - 16..].chars().all(|x| x.is_digit(16));
+        write!(out, "{}", prefix)?;
+
+        let (name, hash) = self.name_and_hash();
+        let hash = hash.filter(|_| !s.strip_function_hash);
+        let badge = self.crate_badge().filter(|_| s.should_print_crate_badges);
+
+        // Shorten the name to fit the line, keeping at least half of it for
+        // the name. Names that still don't fit are wrapped, indented like the
+        // source location. Without a width, names are printed in full.
+        let prefix_width = prefix.chars().count();
+        let suffix_width = hash.map_or(0, |x| x.chars().count())
+            + badge.as_ref().map_or(0, |x| x.chars().count() + 1);
+        let name = match width {
+            Some(width) => layout::elide_generics(
+                name,
+                width
+                    .saturating_sub(prefix_width + suffix_width)
+                    .max(width / 2),
+            ),
+            None => Cow::Borrowed(name),
+        };
+        let lines = match width {
+            Some(width) => layout::wrap_name(&name, width.saturating_sub(prefix_width), width - 4),
+            None => vec![&*name],
+        };
 
         // Print function name.
         out.set_color(if is_dependency_code {
@@ -388,38 +685,49 @@ impl Frame {
             &s.colors.crate_code
         })?;
 
-        if has_hash_suffix {
-            write!(out, "{}", &name[..name.len() - 19])?;
-            if s.strip_function_hash {
-                writeln!(out)?;
+        write!(out, "{}", lines.join("\n    "))?;
+        if let Some(hash) = hash {
+            out.set_color(if is_dependency_code {
+                &s.colors.dependency_code_hash
             } else {
-                out.set_color(if is_dependency_code {
-                    &s.colors.dependency_code_hash
-                } else {
-                    &s.colors.crate_code_hash
-                })?;
-                writeln!(out, "{}", &name[name.len() - 19..])?;
-            }
-        } else {
-            writeln!(out, "{}", name)?;
+                &s.colors.crate_code_hash
+            })?;
+            write!(out, "{}", hash)?;
+        }
+
+        // Print the owning crate, if requested and known.
+        if let Some(badge) = badge {
+            out.set_color(&s.colors.crate_badge)?;
+            write!(out, " {}", badge)?;
         }
 
         out.reset()?;
+        writeln!(out)?;
 
         // Print source location, if known.
         if let Some(ref file) = self.filename {
-            let filestr = file.to_str().unwrap_or("<bad utf8>");
+            let filestr = s.display_path(file);
             let lineno = self
                 .lineno
                 .map_or("<unknown line>".to_owned(), |x| x.to_string());
-            writeln!(out, "    at {}:{}", filestr, lineno)?;
+            write!(out, "    at ")?;
+            let is_link = s.start_hyperlink(out, file, self.lineno, self.colno)?;
+            write!(out, "{}:{}", filestr, lineno)?;
+            if is_link {
+                out.set_hyperlink(&HyperlinkSpec::close())?;
+            }
+            writeln!(out)?;
         } else {
             writeln!(out, "    at <unknown source file>")?;
         }
 
-        // Maybe print source.
-        if s.current_verbosity() >= Verbosity::Full {
-            self.print_source_if_avail(out, s)?;
+        // Print the permalink into the repository, if configured.
+        if let Some(url) = s.permalink(self) {
+            write!(out, "    see ")?;
+            out.set_color(&s.colors.permalink)?;
+            write!(out, "{}", url)?;
+            out.reset()?;
+            writeln!(out)?;
         }
 
         Ok(())
@@ -439,12 +747,189 @@ pub fn default_frame_filter(frames: &mut Vec<&Frame>) {
     let bottom_cutoff = frames
         .iter()
         .position(|x| x.is_runtime_init_code())
-        .unwrap_or_else(|| frames.len());
+        .unwrap_or(frames.len());
 
     let rng = top_cutoff..=bottom_cutoff;
     frames.retain(|x| rng.contains(&x.n))
 }
 
+/// Remove the `[<hash>]` disambiguators that v0 mangling adds to crate names.
+fn strip_disambiguators(name: &str) -> Cow<'_, str> {
+    if !name.contains('[') {
+        return Cow::Borrowed(name);
+    }
+
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find('[') {
+        let (before, after) = rest.split_at(start);
+        out.push_str(before);
+
+        let is_disambiguator = before.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            && after[1..].find(']').is_some_and(|end| {
+                end > 0 && after[1..=end].chars().all(|c| c.is_ascii_hexdigit())
+            });
+        if is_disambiguator {
+            rest = &after[after.find(']').unwrap() + 1..];
+        } else {
+            out.push('[');
+            rest = &after[1..];
+        }
+    }
+    out.push_str(rest);
+
+    Cow::Owned(out)
+}
+
+/// Percent-encode a path for use in URLs, keeping unreserved characters and
+/// separators.
+fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded += &format!("%{:02X}", byte),
+        }
+    }
+    encoded
+}
+
+/// Shorten a path into a cargo registry to `<name>-<version>/<file>` and a path
+/// into a git checkout to `<repository>@<revision>/<file>`.
+fn shorten_cargo_path(path: &Path) -> Option<String> {
+    let components = path
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    let is = |i: usize, name: &str| components.get(i).is_some_and(|x| x == name);
+
+    let i = (1..components.len()).find(|&i| {
+        (is(i - 1, "registry") && is(i, "src")) || (is(i - 1, "git") && is(i, "checkouts"))
+    })?;
+    let (head, rest) = match &*components[i] {
+        // `registry/src/<index>/<name>-<version>/...`
+        "src" => (components.get(i + 2)?.to_string(), components.get(i + 3..)?),
+        // `git/checkouts/<repository>-<hash>/<revision>/...`
+        _ => {
+            let repository = components.get(i + 1)?;
+            let repository = repository.rsplit_once('-').map_or(&**repository, |x| x.0);
+            let revision = components.get(i + 2)?;
+            (
+                format!("{}@{}", repository, revision),
+                components.get(i + 3..)?,
+            )
+        }
+    };
+
+    Some(rest.iter().fold(head, |path, x| path + "/" + x))
+}
+
+/// Find a sequence of frames at the start of `frames` that is immediately
+/// repeated at least twice, as produced by (mutual) recursion.
+///
+/// Returns the length of the sequence and the number of frames in the
+/// repetitions following its first occurrence.
+fn find_recursion(frames: &[&Frame]) -> Option<(usize, usize)> {
+    const MAX_PERIOD: usize = 32;
+    const MIN_OCCURRENCES: usize = 3;
+
+    let same_location =
+        |a: &Frame, b: &Frame| a.name == b.name && a.filename == b.filename && a.lineno == b.lineno;
+
+    (1..=MAX_PERIOD.min(frames.len() / MIN_OCCURRENCES)).find_map(|period| {
+        // Repetitions must not be interrupted by filtered frames.
+        let repeated = (period..frames.len())
+            .take_while(|&i| {
+                frames[i].n == frames[i - 1].n + 1 && same_location(frames[i], frames[i - period])
+            })
+            .count();
+        let repeated = repeated / period * period;
+        Some((period, repeated)).filter(|_| repeated / period + 1 >= MIN_OCCURRENCES)
+    })
+}
+
+/// Flatten a backtrace into one `Frame` per resolved symbol, numbered from 1.
+fn collect_frames(trace: &backtrace::Backtrace) -> Vec<Frame> {
+    trace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols().iter().map(move |sym| (frame.ip(), sym)))
+        .zip(1usize..)
+        .map(|((ip, sym), n)| Frame {
+            name: sym.name().map(|x| x.to_string()),
+            lineno: sym.lineno(),
+            colno: sym.colno(),
+            filename: sym.filename().map(|x| x.into()),
+            n,
+            ip: ip as usize,
+        })
+        .collect()
+}
+
+/// Recover the frames of a `std` backtrace from its alternate `Display` output,
+/// i.e. `format!("{:#}", trace)`.
+///
+/// `std::backtrace::Backtrace` doesn't expose its frames on stable, but the
+/// `{:#}` format lists every symbol as `N: 0xIP - name`, optionally followed by
+/// an `at file:line:column` line. Backtraces that weren't captured yield no
+/// frames.
+fn collect_std_frames(trace: &str) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = vec![];
+
+    for line in trace.lines() {
+        let line = line.trim_start();
+
+        if let Some(location) = line.strip_prefix("at ") {
+            let frame = match frames.last_mut() {
+                Some(frame) => frame,
+                None => continue,
+            };
+
+            // Strip the column and line number, taking care of paths that contain ':'.
+            let mut location = location;
+            let mut numbers = vec![];
+            while let Some((rest, num)) = location.rsplit_once(':') {
+                match num.parse::<u32>() {
+                    Ok(num) if numbers.len() < 2 => numbers.push(num),
+                    _ => break,
+                }
+                location = rest;
+            }
+
+            frame.filename = Some(location.into());
+            frame.lineno = numbers.last().copied();
+            frame.colno = (numbers.len() == 2).then(|| numbers[0]);
+            continue;
+        }
+
+        let (ip, name) = match line
+            .split_once(": ")
+            .and_then(|(_, rest)| rest.trim_start().split_once(" - "))
+        {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let ip = match ip.strip_prefix("0x").map(|x| usize::from_str_radix(x, 16)) {
+            Some(Ok(ip)) => ip,
+            _ => continue,
+        };
+
+        frames.push(Frame {
+            n: frames.len() + 1,
+            name: Some(name.to_owned()).filter(|x| x != "<unknown>"),
+            lineno: None,
+            colno: None,
+            filename: None,
+            ip,
+        });
+    }
+
+    frames
+}
+
 // ============================================================================================== //
 // [BacktracePrinter]                                                                             //
 // ============================================================================================== //
@@ -463,6 +948,14 @@ pub struct ColorScheme {
     pub crate_code: ColorSpec,
     pub crate_code_hash: ColorSpec,
     pub selected_src_ln: ColorSpec,
+    pub crate_badge: ColorSpec,
+    pub src_keyword: ColorSpec,
+    pub src_string: ColorSpec,
+    pub src_comment: ColorSpec,
+    pub src_lifetime: ColorSpec,
+    pub src_macro: ColorSpec,
+    pub dependency_src_ln: ColorSpec,
+    pub permalink: ColorSpec,
 }
 
 impl ColorScheme {
@@ -489,6 +982,18 @@ impl ColorScheme {
             crate_code: Self::cs(Some(Color::Red), true, false),
             crate_code_hash: Self::cs(Some(Color::Black), true, false),
             selected_src_ln: Self::cs(None, false, true),
+            crate_badge: Self::cs(Some(Color::Yellow), false, false),
+            src_keyword: Self::cs(Some(Color::Blue), true, false),
+            src_string: Self::cs(Some(Color::Green), false, false),
+            src_comment: Self::cs(Some(Color::Black), true, false),
+            src_lifetime: Self::cs(Some(Color::Cyan), false, false),
+            src_macro: Self::cs(Some(Color::Magenta), true, false),
+            dependency_src_ln: {
+                let mut cs = ColorSpec::new();
+                cs.set_dimmed(true);
+                cs
+            },
+            permalink: Self::cs(Some(Color::Blue), false, false),
         }
     }
 }
@@ -502,7 +1007,7 @@ impl Default for ColorScheme {
 #[deprecated(since = "0.4.0", note = "Use `BacktracePrinter` instead.")]
 pub type Settings = BacktracePrinter;
 
-/// Pretty-printer for backtraces and [`PanicInfo`](PanicInfo) structs.
+/// Pretty-printer for backtraces and [`PanicInfo`](std::panic::PanicInfo) structs.
 #[derive(Clone)]
 pub struct BacktracePrinter {
     message: String,
@@ -513,6 +1018,26 @@ pub struct BacktracePrinter {
     colors: ColorScheme,
     filters: Vec<Arc<FilterCallback>>,
     should_print_addresses: bool,
+    should_print_crate_badges: bool,
+    should_collapse_dependency_frames: bool,
+    should_fold_recursion: bool,
+    should_highlight_source: bool,
+    source_context: (u32, u32),
+    should_mark_column: bool,
+    path_remappings: Vec<(PathBuf, PathBuf)>,
+    should_resolve_std_sources: bool,
+    should_shorten_dependency_paths: bool,
+    workspace_root: Option<PathBuf>,
+    hyperlink_template: Option<String>,
+    repository_url: Option<String>,
+    commit: Option<String>,
+    width: Option<usize>,
+    previous_hook: PreviousHook,
+    crash_reports: Option<CrashReportConfig>,
+    crate_names: Vec<String>,
+    crate_paths: Vec<PathBuf>,
+    filter_rules: Vec<FilterRule>,
+    env_filter_rules: &'static [FilterRule],
 }
 
 impl Default for BacktracePrinter {
@@ -526,6 +1051,26 @@ impl Default for BacktracePrinter {
             is_panic_handler: false,
             filters: vec![Arc::new(default_frame_filter)],
             should_print_addresses: false,
+            should_print_crate_badges: false,
+            should_collapse_dependency_frames: false,
+            should_fold_recursion: true,
+            should_highlight_source: false,
+            source_context: (2, 2),
+            should_mark_column: false,
+            path_remappings: vec![],
+            should_resolve_std_sources: true,
+            should_shorten_dependency_paths: true,
+            workspace_root: None,
+            hyperlink_template: None,
+            repository_url: None,
+            commit: None,
+            width: None,
+            previous_hook: PreviousHook::Discard,
+            crash_reports: None,
+            crate_names: vec![],
+            crate_paths: vec![],
+            filter_rules: vec![],
+            env_filter_rules: FilterRule::from_env_once(),
         }
     }
 }
@@ -539,6 +1084,32 @@ impl std::fmt::Debug for BacktracePrinter {
             .field("strip_function_hash", &self.strip_function_hash)
             .field("is_panic_handler", &self.is_panic_handler)
             .field("print_addresses", &self.should_print_addresses)
+            .field("crate_badges", &self.should_print_crate_badges)
+            .field(
+                "collapse_dependency_frames",
+                &self.should_collapse_dependency_frames,
+            )
+            .field("fold_recursion", &self.should_fold_recursion)
+            .field("highlight_source", &self.should_highlight_source)
+            .field("source_context", &self.source_context)
+            .field("mark_column", &self.should_mark_column)
+            .field("path_remappings", &self.path_remappings)
+            .field("resolve_std_sources", &self.should_resolve_std_sources)
+            .field(
+                "shorten_dependency_paths",
+                &self.should_shorten_dependency_paths,
+            )
+            .field("workspace_root", &self.workspace_root)
+            .field("hyperlinks", &self.hyperlink_template)
+            .field("repository_url", &self.repository_url)
+            .field("commit", &self.commit)
+            .field("width", &self.width)
+            .field("previous_hook", &self.previous_hook)
+            .field("crash_reports", &self.crash_reports)
+            .field("crate_names", &self.crate_names)
+            .field("crate_paths", &self.crate_paths)
+            .field("filter_rules", &self.filter_rules)
+            .field("env_filter_rules", &self.env_filter_rules)
             .field("colors", &self.colors)
             .finish()
     }
@@ -599,9 +1170,76 @@ impl BacktracePrinter {
         self
     }
 
-    /// Add a custom filter to the set of frame filters
+    /// Controls whether the crate owning each frame, and its version if known,
+    /// is printed next to the function name, e.g. `[tokio 1.38.0]`.
     ///
-    /// Filters are run in the order they are added.
+    /// See [`Frame::crate_name`] and [`Frame::crate_version`].
+    ///
+    /// Defaults to `false`.
+    pub fn crate_badges(mut self, val: bool) -> Self {
+        self.should_print_crate_badges = val;
+        self
+    }
+
+    /// Controls whether runs of adjacent dependency frames from the same crate
+    /// are folded into a single line such as `⋮ 14 frames in tokio ⋮`.
+    ///
+    /// Collapsed frames are shown in full at [`Verbosity::Full`].
+    ///
+    /// Defaults to `false`.
+    pub fn collapse_dependency_frames(mut self, val: bool) -> Self {
+        self.should_collapse_dependency_frames = val;
+        self
+    }
+
+    /// Controls whether sequences of frames repeated by recursion are printed
+    /// only once, followed by a line such as
+    /// `⋮ previous 3 frames repeated 1204 more times ⋮`.
+    ///
+    /// Only sequences of up to 32 frames occurring at least three times in a
+    /// row are folded.
+    ///
+    /// Defaults to `true`.
+    pub fn fold_recursion(mut self, val: bool) -> Self {
+        self.should_fold_recursion = val;
+        self
+    }
+
+    /// Controls whether keywords, strings, comments, lifetimes and macros in
+    /// source snippets are highlighted using the `src_*` colors of the
+    /// [`ColorScheme`].
+    ///
+    /// Defaults to `false`.
+    pub fn highlight_source(mut self, val: bool) -> Self {
+        self.should_highlight_source = val;
+        self
+    }
+
+    /// Controls how many lines of source are shown before and after the
+    /// location of a frame at [`Verbosity::Full`].
+    ///
+    /// Defaults to `(2, 2)`.
+    pub fn source_context(mut self, before: u32, after: u32) -> Self {
+        self.source_context = (before, after);
+        self
+    }
+
+    /// Controls whether a caret is drawn below the column of the location of
+    /// a frame in source snippets, if the column is known.
+    ///
+    /// Defaults to `false`.
+    pub fn mark_column(mut self, val: bool) -> Self {
+        self.should_mark_column = val;
+        self
+    }
+
+    /// Replace the prefix `from` of source file paths with `to`, mirroring
+    /// `rustc`'s `--remap-path-prefix`.
+    ///
+    /// Remapped paths are used to look up source snippets and are shown in
+    /// place of the original ones. This makes it possible to see source
+    /// snippets of binaries built elsewhere, e.g. in a container. If multiple
+    /// remappings match a path, the one added last is applied.
     ///
     /// # Example
     ///
@@ -609,167 +1247,828 @@ impl BacktracePrinter {
     /// use color_backtrace::{default_output_stream, BacktracePrinter};
     ///
     /// BacktracePrinter::new()
-    ///     .add_frame_filter(Box::new(|frames| {
-    ///         frames.retain(|x| matches!(&x.name, Some(n) if !n.starts_with("blabla")))
-    ///     }))
+    ///     .add_path_remapping("/build/workspace", env!("CARGO_MANIFEST_DIR"))
     ///     .install(default_output_stream());
     /// ```
-    pub fn add_frame_filter(mut self, filter: Box<FilterCallback>) -> Self {
-        self.filters.push(filter.into());
+    ///
+    /// Defaults to no remappings.
+    pub fn add_path_remapping(mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> Self {
+        self.path_remappings.push((from.into(), to.into()));
         self
     }
 
-    /// Clears all filters associated with this printer, including the default filter
-    pub fn clear_frame_filters(mut self) -> Self {
-        self.filters.clear();
+    /// Controls whether source snippets of standard library frames, located
+    /// at `/rustc/<commit>/library/...`, are read from the `rust-src`
+    /// component of the local rustup toolchain built from the same commit.
+    ///
+    /// The `RUST_SRC_PATH` env variable can point to the `library` directory
+    /// of the sources explicitly. The displayed paths are not affected.
+    ///
+    /// Defaults to `true`.
+    pub fn resolve_std_sources(mut self, val: bool) -> Self {
+        self.should_resolve_std_sources = val;
         self
     }
-}
 
-/// Routines for putting the panic printer to use.
-impl BacktracePrinter {
-    /// Install the `color_backtrace` handler with default settings.
+    /// Controls whether paths into the cargo registry and git checkouts are
+    /// shortened to `<name>-<version>/src/file.rs` and
+    /// `<repository>@<revision>/src/file.rs` respectively.
     ///
-    /// Output streams can be created via `default_output_stream()` or
-    /// using any other stream that implements
-    /// [`termcolor::WriteColor`](termcolor::WriteColor).
-    pub fn install(self, out: impl WriteColor + Sync + Send + 'static) {
-        std::panic::set_hook(self.into_panic_handler(out))
+    /// Only the displayed paths are affected, JSON output keeps the full paths.
+    ///
+    /// Defaults to `true`.
+    pub fn shorten_dependency_paths(mut self, val: bool) -> Self {
+        self.should_shorten_dependency_paths = val;
+        self
     }
 
-    /// Create a `color_backtrace` panic handler from this panic printer.
+    /// Display the paths of source files below `root` relative to it.
     ///
-    /// This can be used if you want to combine the handler with other handlers.
-    pub fn into_panic_handler(
-        mut self,
-        out: impl WriteColor + Sync + Send + 'static,
-    ) -> Box<dyn Fn(&PanicInfo<'_>) + 'static + Sync + Send> {
-        self.is_panic_handler = true;
-        let out_stream_mutex = Mutex::new(out);
-        Box::new(move |pi| {
-            let mut lock = out_stream_mutex.lock().unwrap();
-            if let Err(e) = self.print_panic_info(pi, &mut *lock) {
-                // Panicking while handling a panic would send us into a deadlock,
-                // so we just print the error to stderr instead.
-                eprintln!("Error while printing panic: {:?}", e);
-            }
-        })
+    /// Only the displayed paths are affected, JSON output keeps the full paths.
+    ///
+    /// Defaults to `None`.
+    pub fn workspace_root(mut self, root: impl Into<Option<PathBuf>>) -> Self {
+        self.workspace_root = root.into();
+        self
     }
 
-    /// Pretty-prints a [`backtrace::Backtrace`](backtrace::Backtrace) to an output stream.
-    pub fn print_trace(&self, trace: &backtrace::Backtrace, out: &mut impl WriteColor) -> IOResult {
-        writeln!(out, "{:━^80}", " BACKTRACE ")?;
-
-        // Collect frame info.
-        let frames: Vec<_> = trace
-            .frames()
+    /// Display the paths of source files relative to the workspace root,
+    /// detected from `CARGO_MANIFEST_DIR` or the current working directory.
+    ///
+    /// The root is the outermost directory with a `Cargo.toml` declaring a
+    /// `[workspace]`, or the nearest directory with a `Cargo.toml` if there is
+    /// none. Nothing changes if no `Cargo.toml` is found. See
+    /// [`workspace_root`](BacktracePrinter::workspace_root).
+    pub fn detect_workspace_root(mut self) -> Self {
+        let start = env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|| env::current_dir().ok());
+        let manifests = start
             .iter()
-            .flat_map(|frame| frame.symbols().iter().map(move |sym| (frame.ip(), sym)))
-            .zip(1usize..)
-            .map(|((ip, sym), n)| Frame {
-                name: sym.name().map(|x| x.to_string()),
-                lineno: sym.lineno(),
-                filename: sym.filename().map(|x| x.into()),
-                n,
-                ip: ip as usize,
-                _private_ctor: (),
-            })
-            .collect();
-
-        let mut filtered_frames = frames.iter().collect();
-        match env::var("COLORBT_SHOW_HIDDEN").ok().as_deref() {
-            Some("1") | Some("on") | Some("y") => (),
-            _ => {
-                for filter in &self.filters {
-                    filter(&mut filtered_frames);
-                }
-            }
-        }
-
-        if filtered_frames.is_empty() {
-            // TODO: Would probably look better centered.
-            return writeln!(out, "<empty backtrace>");
-        }
-
-        // Don't let filters mess with the order.
-        filtered_frames.sort_by_key(|x| x.n);
+            .flat_map(|x| x.ancestors())
+            .filter(|x| x.join("Cargo.toml").is_file())
+            .collect::<Vec<_>>();
 
-        macro_rules! print_hidden {
-            ($n:expr) => {
-                out.set_color(&self.colors.frames_omitted_msg)?;
-                let n = $n;
-                let text = format!(
-                    "{decorator} {n} frame{plural} hidden {decorator}",
-                    n = n,
-                    plural = if n == 1 { "" } else { "s" },
-                    decorator = "⋮",
-                );
-                writeln!(out, "{:^80}", text)?;
-                out.reset()?;
-            };
-        }
-
-        let mut last_n = 0;
-        for frame in &filtered_frames {
-            let frame_delta = frame.n - last_n - 1;
-            if frame_delta != 0 {
-                print_hidden!(frame_delta);
-            }
-            frame.print(frame.n, out, self)?;
-            last_n = frame.n;
-        }
+        let is_workspace = |dir: &Path| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .is_ok_and(|x| x.lines().any(|x| x.trim() == "[workspace]"))
+        };
+        let root = manifests
+            .iter()
+            .rev()
+            .find(|x| is_workspace(x))
+            .or(manifests.first());
 
-        let last_filtered_n = filtered_frames.last().unwrap().n;
-        let last_unfiltered_n = frames.last().unwrap().n;
-        if last_filtered_n < last_unfiltered_n {
-            print_hidden!(last_unfiltered_n - last_filtered_n);
+        if let Some(root) = root {
+            self.workspace_root = Some(root.to_path_buf());
         }
-
-        Ok(())
+        self
     }
 
-    /// Pretty-print a backtrace to a `String`, using VT100 color codes.
-    pub fn format_trace_to_string(&self, trace: &backtrace::Backtrace) -> IOResult<String> {
-        // TODO: should we implicitly enable VT100 support on Windows here?
-        let mut ansi = Ansi::new(vec![]);
-        self.print_trace(trace, &mut ansi)?;
-        Ok(String::from_utf8(ansi.into_inner()).unwrap())
+    /// Turn source locations into OSC 8 hyperlinks, for output streams that
+    /// support them, using the given URL template.
+    ///
+    /// The placeholders `{path}`, `{line}` and `{column}` are replaced with the
+    /// percent-encoded absolute path of the source file, the line and the
+    /// column. Some useful templates are:
+    ///
+    /// - `file://{path}`
+    /// - `vscode://file{path}:{line}:{column}`
+    /// - `idea://open?file={path}&line={line}`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// BacktracePrinter::new()
+    ///     .hyperlinks("vscode://file{path}:{line}:{column}".to_owned())
+    ///     .install(default_output_stream());
+    /// ```
+    ///
+    /// Defaults to `None`.
+    pub fn hyperlinks(mut self, template: impl Into<Option<String>>) -> Self {
+        self.hyperlink_template = template.into();
+        self
     }
 
-    /// Pretty-prints a [`PanicInfo`](PanicInfo) struct to an output stream.
-    pub fn print_panic_info(&self, pi: &PanicInfo, out: &mut impl WriteColor) -> IOResult {
+    /// Link frames of crate code to the repository using the given URL
+    /// template, so reports can be followed up without the exact checkout.
+    ///
+    /// The placeholders `{commit}`, `{path}` and `{line}` are replaced with
+    /// the [`commit`](BacktracePrinter::commit), the percent-encoded path of
+    /// the source file relative to the
+    /// [`workspace_root`](BacktracePrinter::workspace_root) and the line.
+    /// Links are only emitted if the commit is known or not part of the
+    /// template. The links are printed below the source location, added to
+    /// JSON frames as `permalink` and wrap the locations in HTML and Markdown
+    /// output. See [`permalink`](BacktracePrinter::permalink).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// BacktracePrinter::new()
+    ///     .detect_workspace_root()
+    ///     .repository_url(
+    ///         "https://github.com/org/repo/blob/{commit}/{path}#L{line}".to_owned(),
+    ///     )
+    ///     .commit(option_env!("GIT_HASH").map(str::to_owned))
+    ///     .install(default_output_stream());
+    /// ```
+    ///
+    /// Defaults to `None`.
+    pub fn repository_url(mut self, template: impl Into<Option<String>>) -> Self {
+        self.repository_url = template.into();
+        self
+    }
+
+    /// The commit the binary was built from, used in the
+    /// [`repository_url`](BacktracePrinter::repository_url) template.
+    ///
+    /// This is usually baked in at build time from a variable set by the
+    /// build script or CI, e.g. `option_env!("GIT_HASH")` or
+    /// `option_env!("GITHUB_SHA")`.
+    ///
+    /// Defaults to `None`.
+    pub fn commit(mut self, commit: impl Into<Option<String>>) -> Self {
+        self.commit = commit.into();
+        self
+    }
+
+    /// Lay the output out for `width` columns.
+    ///
+    /// Function names that don't fit are shortened by eliding generic
+    /// arguments, innermost first, and wrapped as a last resort. Widths below
+    /// 40 columns are treated as 40.
+    ///
+    /// With `None`, output supporting colors is laid out for the terminal
+    /// connected to stderr, if any, as it most likely is that terminal. Other
+    /// output, like the strings of
+    /// [`format_trace_to_string`](BacktracePrinter::format_trace_to_string)
+    /// and crash reports, prints names in full.
+    ///
+    /// Defaults to `None`.
+    pub fn width(mut self, width: impl Into<Option<usize>>) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    /// Controls whether `install` keeps the previously installed panic hook
+    /// and calls it before or after printing the panic.
+    ///
+    /// Chained hooks can be restored with [`restore_previous_hook`]. This has
+    /// no effect on [`into_panic_handler`](BacktracePrinter::into_panic_handler).
+    ///
+    /// Defaults to `PreviousHook::Discard`.
+    pub fn previous_hook(mut self, mode: PreviousHook) -> Self {
+        self.previous_hook = mode;
+        self
+    }
+
+    /// Persist every panic caught by the panic handler as a report file, in
+    /// addition to printing it to the output stream.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter, CrashReportConfig};
+    ///
+    /// BacktracePrinter::new()
+    ///     .crash_reports(CrashReportConfig::new("/var/log/my-daemon").json(true))
+    ///     .install(default_output_stream());
+    /// ```
+    ///
+    /// Defaults to `None`.
+    pub fn crash_reports(mut self, config: impl Into<Option<CrashReportConfig>>) -> Self {
+        self.crash_reports = config.into();
+        self
+    }
+
+    /// Treat functions of the crate `name` as crate code rather than
+    /// dependency code.
+    ///
+    /// Once any crate name or path is configured, frames are classified by
+    /// these alone instead of the heuristic in
+    /// [`Frame::is_dependency_code`]: everything not matching is considered
+    /// dependency code. Dashes in `name` are treated as underscores, so
+    /// package names can be passed as-is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// BacktracePrinter::new()
+    ///     .add_crate_name(env!("CARGO_PKG_NAME"))
+    ///     .add_crate_name("my-workspace-utils")
+    ///     .install(default_output_stream());
+    /// ```
+    ///
+    /// Defaults to no crate names.
+    pub fn add_crate_name(mut self, name: impl Into<String>) -> Self {
+        self.crate_names.push(name.into().replace('-', "_"));
+        self
+    }
+
+    /// Treat functions defined in files below `prefix` as crate code rather
+    /// than dependency code.
+    ///
+    /// This is useful to claim whole workspaces, e.g. by passing
+    /// `env!("CARGO_MANIFEST_DIR")`, or vendored dependencies. See
+    /// [`add_crate_name`](BacktracePrinter::add_crate_name) for how this
+    /// affects the classification.
+    ///
+    /// Defaults to no crate paths.
+    pub fn add_crate_path(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.crate_paths.push(prefix.into());
+        self
+    }
+
+    /// Add a custom filter to the set of frame filters
+    ///
+    /// Filters are run in the order they are added.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// BacktracePrinter::new()
+    ///     .add_frame_filter(Box::new(|frames| {
+    ///         frames.retain(|x| matches!(&x.name, Some(n) if !n.starts_with("blabla")))
+    ///     }))
+    ///     .install(default_output_stream());
+    /// ```
+    pub fn add_frame_filter(mut self, filter: Box<FilterCallback>) -> Self {
+        self.filters.push(filter.into());
+        self
+    }
+
+    /// Add a declarative filter rule, applied after the frame filters.
+    ///
+    /// Rules from the environment, see [`FilterRule::from_env`], are applied
+    /// after those added here.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// BacktracePrinter::new()
+    ///     .add_filter_rule("hide crate:tokio".parse().unwrap())
+    ///     .add_filter_rule("show name:*::parse_*".parse().unwrap())
+    ///     .install(default_output_stream());
+    /// ```
+    pub fn add_filter_rule(mut self, rule: FilterRule) -> Self {
+        self.filter_rules.push(rule);
+        self
+    }
+
+    /// Add the rules of a [`FilterPreset`], hiding the frames of a common
+    /// runtime or framework.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter, FilterPreset};
+    ///
+    /// BacktracePrinter::new()
+    ///     .add_filter_preset(FilterPreset::Tokio)
+    ///     .add_filter_preset(FilterPreset::Futures)
+    ///     .install(default_output_stream());
+    /// ```
+    pub fn add_filter_preset(mut self, preset: FilterPreset) -> Self {
+        self.filter_rules.extend(preset.rules());
+        self
+    }
+
+    /// Clears all filters associated with this printer, including the default filter
+    pub fn clear_frame_filters(mut self) -> Self {
+        self.filters.clear();
+        self
+    }
+}
+
+/// Routines for putting the panic printer to use.
+impl BacktracePrinter {
+    /// Install the `color_backtrace` handler with default settings.
+    ///
+    /// Output streams can be created via `default_output_stream()` or
+    /// using any other stream that implements
+    /// [`termcolor::WriteColor`](termcolor::WriteColor).
+    pub fn install(self, out: impl WriteColor + Sync + Send + 'static) {
+        let mode = self.previous_hook;
+        let handler = self.into_panic_handler(out);
+        if mode == PreviousHook::Discard {
+            return std::panic::set_hook(handler);
+        }
+
+        let previous: Arc<PanicHook> = std::panic::take_hook().into();
+        PREVIOUS_HOOKS.lock().unwrap().push(previous.clone());
+        std::panic::set_hook(Box::new(move |pi| {
+            if mode == PreviousHook::CallBefore {
+                previous(pi);
+            }
+            handler(pi);
+            if mode == PreviousHook::CallAfter {
+                previous(pi);
+            }
+        }))
+    }
+
+    /// Install the `color_backtrace` handler for the current thread until the
+    /// returned guard is dropped.
+    ///
+    /// Panics on other threads, and on this thread once the guard is dropped,
+    /// go to the previously installed hook again. This makes it possible to
+    /// get pretty panics inside specific tests without replacing the test
+    /// harness' hook for the whole process, even with tests running in
+    /// parallel. [`PreviousHook::CallBefore`] and [`PreviousHook::CallAfter`]
+    /// are honored for the scoped handler, too.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::{default_output_stream, BacktracePrinter};
+    ///
+    /// let _guard = BacktracePrinter::new().install_scoped(default_output_stream());
+    /// // Panics on this thread are printed by color-backtrace until `_guard` goes
+    /// // out of scope.
+    /// ```
+    pub fn install_scoped(self, out: impl WriteColor + Sync + Send + 'static) -> PanicHookGuard {
+        let mode = self.previous_hook;
+        let handler: Arc<PanicHook> = self.into_panic_handler(out).into();
+
+        let mut dispatch = SCOPED_DISPATCH.lock().unwrap_or_else(|e| e.into_inner());
+        if dispatch.previous.is_none() {
+            let previous: Arc<PanicHook> = std::panic::take_hook().into();
+            dispatch.previous = Some(previous.clone());
+            std::panic::set_hook(Box::new(move |pi| {
+                let scoped = SCOPED_HANDLERS
+                    .try_with(|handlers| handlers.borrow().last().cloned())
+                    .ok()
+                    .flatten();
+                match scoped {
+                    Some((_, handler, mode)) => {
+                        if mode == PreviousHook::CallBefore {
+                            previous(pi);
+                        }
+                        handler(pi);
+                        if mode == PreviousHook::CallAfter {
+                            previous(pi);
+                        }
+                    }
+                    None => previous(pi),
+                }
+            }));
+        }
+        dispatch.guards += 1;
+
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        SCOPED_HANDLERS.with(|handlers| handlers.borrow_mut().push((id, handler, mode)));
+
+        PanicHookGuard {
+            id,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Create a `color_backtrace` panic handler from this panic printer.
+    ///
+    /// This can be used if you want to combine the handler with other handlers.
+    pub fn into_panic_handler(
+        mut self,
+        out: impl WriteColor + Sync + Send + 'static,
+    ) -> Box<dyn Fn(&PanicHookInfo<'_>) + 'static + Sync + Send> {
+        self.is_panic_handler = true;
+        let out_stream_mutex = Mutex::new(out);
+        Box::new(move |pi| {
+            let mut lock = out_stream_mutex.lock().unwrap();
+            if let Err(e) = self.print_panic_info(pi, &mut *lock) {
+                // Panicking while handling a panic would send us into a deadlock,
+                // so we just print the error to stderr instead.
+                eprintln!("Error while printing panic: {:?}", e);
+            }
+            if let Err(e) = self.write_crash_reports(pi) {
+                eprintln!("Error while writing crash report: {:?}", e);
+            }
+        })
+    }
+
+    /// Pretty-prints a [`backtrace::Backtrace`](backtrace::Backtrace) to an output stream.
+    pub fn print_trace(&self, trace: &backtrace::Backtrace, out: &mut impl WriteColor) -> IOResult {
+        self.render_trace(trace, &mut TerminalRenderer::new(self, out))
+    }
+
+    /// Pretty-prints a [`std::backtrace::Backtrace`](std::backtrace::Backtrace) to an output
+    /// stream.
+    ///
+    /// The frames are recovered from the backtrace's `Display` output, so
+    /// instruction pointers are only known if std prints them. Backtraces
+    /// that weren't captured are printed as empty.
+    pub fn print_std_trace(
+        &self,
+        trace: &std::backtrace::Backtrace,
+        out: &mut impl WriteColor,
+    ) -> IOResult {
+        self.render_std_trace(trace, &mut TerminalRenderer::new(self, out))
+    }
+
+    /// Renders a [`backtrace::Backtrace`](backtrace::Backtrace) using a custom
+    /// [`TraceRenderer`].
+    ///
+    /// Frames are collected and filtered exactly as in
+    /// [`print_trace`](BacktracePrinter::print_trace), only the formatting is
+    /// left to the renderer.
+    pub fn render_trace(
+        &self,
+        trace: &backtrace::Backtrace,
+        renderer: &mut impl TraceRenderer,
+    ) -> IOResult {
+        self.render_frames(collect_frames(trace), renderer)
+    }
+
+    /// Renders a [`std::backtrace::Backtrace`](std::backtrace::Backtrace) using a custom
+    /// [`TraceRenderer`].
+    pub fn render_std_trace(
+        &self,
+        trace: &std::backtrace::Backtrace,
+        renderer: &mut impl TraceRenderer,
+    ) -> IOResult {
+        self.render_frames(collect_std_frames(&format!("{:#}", trace)), renderer)
+    }
+
+    fn render_frames(&self, mut frames: Vec<Frame>, renderer: &mut impl TraceRenderer) -> IOResult {
+        for frame in &mut frames {
+            if let Some(Cow::Owned(remapped)) =
+                frame.filename.as_deref().map(|x| self.remap_path(x))
+            {
+                frame.filename = Some(remapped);
+            }
+        }
+
+        let frames = &frames[..];
+        let filtered_frames = self.filter_frames(frames);
+
+        renderer.header(frames, &filtered_frames)?;
+
+        if filtered_frames.is_empty() {
+            renderer.empty()?;
+            return renderer.footer();
+        }
+
+        // Collapsed runs are expanded again at full verbosity.
+        let collapse =
+            self.should_collapse_dependency_frames && self.current_verbosity() < Verbosity::Full;
+
+        let mut last_n = 0;
+        let mut i = 0;
+        // Start, length and period of the next repetitions to fold.
+        let mut fold: Option<(usize, usize, usize)> = None;
+        while let Some(frame) = filtered_frames.get(i) {
+            // Frame numbers are 1 based indices into `frames`.
+            let hidden = &frames[last_n..frame.n - 1];
+            if !hidden.is_empty() {
+                renderer.hidden_frames(hidden)?;
+            }
+
+            // The first occurrence of a recursive sequence is rendered as
+            // usual, the repetitions following it are folded.
+            if fold.is_none() && self.should_fold_recursion {
+                fold = find_recursion(&filtered_frames[i..])
+                    .map(|(period, len)| (i + period, len, period));
+            }
+            if let Some((_, len, period)) = fold.filter(|x| x.0 == i) {
+                let repeated = &filtered_frames[i..i + len];
+                renderer.repeated_frames(repeated, period)?;
+                last_n = repeated[len - 1].n;
+                i += len;
+                fold = None;
+                continue;
+            }
+
+            let end = fold.map_or(filtered_frames.len(), |x| x.0);
+            if let Some((run, krate)) = self
+                .dependency_run(&filtered_frames[i..end])
+                .filter(|_| collapse)
+            {
+                renderer.collapsed_frames(&filtered_frames[i..i + run], &krate)?;
+                last_n = filtered_frames[i + run - 1].n;
+                i += run;
+                continue;
+            }
+
+            renderer.frame(frame)?;
+
+            if self.current_verbosity() >= Verbosity::Full {
+                for (lineno, line) in self.source_snippet(frame)? {
+                    renderer.source_line(frame, lineno, &line)?;
+                }
+            }
+
+            last_n = frame.n;
+            i += 1;
+        }
+
+        let hidden = &frames[last_n..];
+        if !hidden.is_empty() {
+            renderer.hidden_frames(hidden)?;
+        }
+
+        renderer.footer()
+    }
+
+    /// Read the source snippet of a frame, using the local standard library
+    /// sources if enabled and available.
+    fn source_snippet(&self, frame: &Frame) -> IOResult<Vec<(u32, String)>> {
+        let filename = match &frame.filename {
+            Some(filename) => filename,
+            None => return Ok(vec![]),
+        };
+
+        let local = Some(filename)
+            .filter(|_| self.should_resolve_std_sources)
+            .and_then(|x| std_src::local_std_source(x));
+        let (before, after) = self.source_context;
+        frame.source_snippet(local.as_deref().unwrap_or(filename), before, after)
+    }
+
+    /// The path of a source file as displayed.
+    fn display_path<'a>(&self, path: &'a Path) -> Cow<'a, str> {
+        if let Some(short) =
+            shorten_cargo_path(path).filter(|_| self.should_shorten_dependency_paths)
+        {
+            return Cow::Owned(short);
+        }
+
+        match self.workspace_root.as_ref().map(|x| path.strip_prefix(x)) {
+            Some(Ok(relative)) => relative.to_string_lossy(),
+            _ => path.to_string_lossy(),
+        }
+    }
+
+    /// Start a hyperlink to a source location if enabled and supported by
+    /// `out`, returning whether one was started.
+    fn start_hyperlink(
+        &self,
+        out: &mut impl WriteColor,
+        path: &Path,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> IOResult<bool> {
+        if !out.supports_hyperlinks() {
+            return Ok(false);
+        }
+
+        match self.hyperlink_url(path, line, column) {
+            Some(url) => {
+                out.set_hyperlink(&HyperlinkSpec::open(url.as_bytes()))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Fill the hyperlink template in for a source location.
+    fn hyperlink_url(&self, path: &Path, line: Option<u32>, column: Option<u32>) -> Option<String> {
+        let template = self.hyperlink_template.as_ref()?;
+        let path = self.local_path(path)?;
+
+        let mut encoded = percent_encode_path(&path.to_string_lossy().replace('\\', "/"));
+        if !encoded.starts_with('/') {
+            encoded.insert(0, '/');
+        }
+
+        Some(
+            template
+                .replace("{path}", &encoded)
+                .replace("{line}", &line.unwrap_or(1).to_string())
+                .replace("{column}", &column.unwrap_or(1).to_string()),
+        )
+    }
+
+    /// The permalink of a frame in the repository configured via
+    /// [`repository_url`](BacktracePrinter::repository_url).
+    ///
+    /// Only crate code is linked, since dependencies and the standard library
+    /// live in other repositories. Absolute paths must be below the
+    /// [`workspace_root`](BacktracePrinter::workspace_root), which is assumed
+    /// to be the root of the repository.
+    pub fn permalink(&self, frame: &Frame) -> Option<String> {
+        let template = self.repository_url.as_ref()?;
+        let file = frame.filename.as_ref()?;
+        if self.is_dependency_frame(frame) {
+            return None;
+        }
+
+        let relative = if file.has_root() {
+            file.strip_prefix(self.workspace_root.as_ref()?).ok()?
+        } else {
+            file.as_path()
+        };
+        let path = percent_encode_path(&relative.to_string_lossy().replace('\\', "/"));
+
+        let url = template
+            .replace("{path}", path.trim_start_matches("./"))
+            .replace("{line}", &frame.lineno.unwrap_or(1).to_string());
+        match &self.commit {
+            Some(commit) => Some(url.replace("{commit}", commit)),
+            // Linking to a branch instead would drift away from the crashed
+            // build, so omit the link entirely.
+            None if url.contains("{commit}") => None,
+            None => Some(url),
+        }
+    }
+
+    /// Resolve a source path to an absolute path on this machine, if possible.
+    ///
+    /// Relative paths, as found in panic locations, are looked up relative to
+    /// the workspace root and the current working directory.
+    fn local_path<'a>(&self, path: &'a Path) -> Option<Cow<'a, Path>> {
+        if let Some(local) = Some(path)
+            .filter(|_| self.should_resolve_std_sources)
+            .and_then(std_src::local_std_source)
+        {
+            return Some(Cow::Owned(local));
+        }
+
+        if path.is_absolute() {
+            return Some(Cow::Borrowed(path));
+        }
+
+        self.workspace_root
+            .iter()
+            .cloned()
+            .chain(env::current_dir().ok())
+            .map(|x| x.join(path))
+            .find(|x| x.exists())
+            .map(Cow::Owned)
+    }
+
+    /// Apply the path remappings to a source file path.
+    ///
+    /// Like with `rustc`, the last matching remapping is applied.
+    fn remap_path<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        let remapping = self
+            .path_remappings
+            .iter()
+            .rev()
+            .find_map(|(from, to)| Some((path.strip_prefix(from).ok()?, to)));
+
+        match remapping {
+            Some((rest, to)) => Cow::Owned(to.join(rest)),
+            None => Cow::Borrowed(path),
+        }
+    }
+
+    /// Determine whether a frame is part of a dependency rather than the
+    /// crate(s) configured via [`add_crate_name`](BacktracePrinter::add_crate_name)
+    /// and [`add_crate_path`](BacktracePrinter::add_crate_path).
+    ///
+    /// Frames matched by a `dependency` [`FilterRule`] are always dependency
+    /// code. Falls back to [`Frame::is_dependency_code`] if neither crate
+    /// names nor paths were configured.
+    pub fn is_dependency_frame(&self, frame: &Frame) -> bool {
+        if self.is_marked_dependency(frame) {
+            return true;
+        }
+
+        if self.crate_names.is_empty() && self.crate_paths.is_empty() {
+            return frame.is_dependency_code();
+        }
+
+        let by_name = frame
+            .symbol_crate()
+            .is_some_and(|krate| self.crate_names.iter().any(|x| x == krate));
+        let by_path = frame
+            .filename
+            .as_ref()
+            .is_some_and(|file| self.crate_paths.iter().any(|x| file.starts_with(x)));
+
+        !by_name && !by_path
+    }
+
+    /// Pretty-print a backtrace to a `String`, using VT100 color codes.
+    pub fn format_trace_to_string(&self, trace: &backtrace::Backtrace) -> IOResult<String> {
+        // TODO: should we implicitly enable VT100 support on Windows here?
+        let mut ansi = Ansi::new(vec![]);
+        let mut renderer = TerminalRenderer::with_width(self, &mut ansi, self.width);
+        self.render_trace(trace, &mut renderer)?;
+        Ok(String::from_utf8(ansi.into_inner()).unwrap())
+    }
+
+    /// Pretty-print a `std` backtrace to a `String`, using VT100 color codes.
+    pub fn format_std_trace_to_string(
+        &self,
+        trace: &std::backtrace::Backtrace,
+    ) -> IOResult<String> {
+        let mut ansi = Ansi::new(vec![]);
+        let mut renderer = TerminalRenderer::with_width(self, &mut ansi, self.width);
+        self.render_std_trace(trace, &mut renderer)?;
+        Ok(String::from_utf8(ansi.into_inner()).unwrap())
+    }
+
+    /// Pretty-prints a [`PanicInfo`](std::panic::PanicInfo) struct to an output stream.
+    pub fn print_panic_info(&self, pi: &PanicHookInfo, out: &mut impl WriteColor) -> IOResult {
         out.set_color(&self.colors.header)?;
         writeln!(out, "{}", self.message)?;
         out.reset()?;
 
         // Print panic message.
-        let payload = pi
-            .payload()
-            .downcast_ref::<String>()
-            .map(String::as_str)
-            .or_else(|| pi.payload().downcast_ref::<&str>().cloned())
-            .unwrap_or("<non string panic payload>");
-
         write!(out, "Message:  ")?;
         out.set_color(&self.colors.msg_loc_prefix)?;
-        writeln!(out, "{}", payload)?;
+        writeln!(out, "{}", panic_payload(pi))?;
         out.reset()?;
 
         // If known, print panic location.
         write!(out, "Location: ")?;
         if let Some(loc) = pi.location() {
+            let is_link = self.start_hyperlink(
+                out,
+                loc.file().as_ref(),
+                Some(loc.line()),
+                Some(loc.column()),
+            )?;
             out.set_color(&self.colors.src_loc)?;
             write!(out, "{}", loc.file())?;
             out.set_color(&self.colors.src_loc_separator)?;
             write!(out, ":")?;
             out.set_color(&self.colors.src_loc)?;
-            writeln!(out, "{}", loc.line())?;
+            write!(out, "{}", loc.line())?;
+            if is_link {
+                out.set_hyperlink(&HyperlinkSpec::close())?;
+            }
             out.reset()?;
+            writeln!(out)?;
         } else {
             writeln!(out, "<unknown>")?;
         }
 
-        // Print some info on how to increase verbosity.
+        self.print_verbosity_hints(out)?;
+
+        if self.current_verbosity() >= Verbosity::Medium {
+            self.print_trace(&backtrace::Backtrace::new(), out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Pretty-prints an error and its chain of [`source`](std::error::Error::source)s
+    /// to an output stream.
+    ///
+    /// Stable Rust offers no way to ask a `dyn Error` for its backtrace, so
+    /// none is printed. Use
+    /// [`print_error_with_trace`](BacktracePrinter::print_error_with_trace)
+    /// if you have access to one.
+    pub fn print_error(&self, err: &dyn std::error::Error, out: &mut impl WriteColor) -> IOResult {
+        out.set_color(&self.colors.header)?;
+        write!(out, "Error:")?;
+        out.reset()?;
+        out.set_color(&self.colors.msg_loc_prefix)?;
+        writeln!(out, " {}", err)?;
+        out.reset()?;
+
+        let mut causes = std::iter::successors(err.source(), |x| x.source()).peekable();
+        if causes.peek().is_some() {
+            writeln!(out, "\nCaused by:")?;
+            for (i, cause) in causes.enumerate() {
+                write!(out, "{:>4}: ", i)?;
+                out.set_color(&self.colors.msg_loc_prefix)?;
+                writeln!(out, "{}", cause)?;
+                out.reset()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pretty-prints an error, its chain of sources and the backtrace captured
+    /// with it to an output stream.
+    ///
+    /// This works well with errors carrying a
+    /// [`std::backtrace::Backtrace`](std::backtrace::Backtrace), e.g.
+    /// `anyhow::Error::backtrace`. The backtrace is printed according to the lib
+    /// verbosity.
+    pub fn print_error_with_trace(
+        &self,
+        err: &dyn std::error::Error,
+        trace: &std::backtrace::Backtrace,
+        out: &mut impl WriteColor,
+    ) -> IOResult {
+        self.print_error(err, out)?;
+        self.print_verbosity_hints(out)?;
+
+        if self.current_verbosity() >= Verbosity::Medium {
+            self.print_std_trace(trace, out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print some info on how to increase verbosity.
+    fn print_verbosity_hints(&self, out: &mut impl WriteColor) -> IOResult {
         if self.current_verbosity() == Verbosity::Minimal {
             write!(out, "\nBacktrace omitted.\n\nRun with ")?;
             out.set_color(&self.colors.env_var)?;
@@ -792,11 +2091,26 @@ impl BacktracePrinter {
             writeln!(out, " to include source snippets.")?;
         }
 
-        if self.current_verbosity() >= Verbosity::Medium {
-            self.print_trace(&backtrace::Backtrace::new(), out)?;
+        Ok(())
+    }
+
+    /// Runs the configured frame filters and rules, unless disabled via
+    /// `COLORBT_SHOW_HIDDEN`.
+    fn filter_frames<'a>(&self, frames: &'a [Frame]) -> Vec<&'a Frame> {
+        let mut filtered_frames = frames.iter().collect();
+        match env::var("COLORBT_SHOW_HIDDEN").ok().as_deref() {
+            Some("1") | Some("on") | Some("y") => (),
+            _ => {
+                for filter in &self.filters {
+                    filter(&mut filtered_frames);
+                }
+                filtered_frames = self.apply_filter_rules(frames, filtered_frames);
+            }
         }
 
-        Ok(())
+        // Don't let filters mess with the order.
+        filtered_frames.sort_by_key(|x| x.n);
+        filtered_frames
     }
 
     fn current_verbosity(&self) -> Verbosity {
@@ -810,6 +2124,347 @@ impl BacktracePrinter {
     fn should_print_addresses(&self) -> bool {
         self.should_print_addresses
     }
+
+    /// The length of the run of adjacent dependency frames from the same crate
+    /// at the start of `frames`, along with the crate's name, if there are at
+    /// least two such frames.
+    fn dependency_run<'a>(&self, frames: &[&'a Frame]) -> Option<(usize, Cow<'a, str>)> {
+        let first = frames.first()?;
+        if !self.is_dependency_frame(first) {
+            return None;
+        }
+
+        let krate = first.crate_name()?;
+        let len = 1 + frames
+            .windows(2)
+            .take_while(|x| {
+                x[1].n == x[0].n + 1
+                    && self.is_dependency_frame(x[1])
+                    && x[1].crate_name().as_deref() == Some(&*krate)
+            })
+            .count();
+
+        Some((len, krate)).filter(|_| len > 1)
+    }
+}
+
+// ============================================================================================== //
+// [Trace rendering]                                                                              //
+// ============================================================================================== //
+
+/// Output format for a filtered backtrace.
+///
+/// [`BacktracePrinter::render_trace`] collects and filters the frames and then
+/// drives the renderer through these callbacks: `header` first, then `frame`
+/// for each frame that survived filtering, each followed by its `source_line`s
+/// and preceded by `hidden_frames` if frames were filtered out in between, and
+/// `footer` last. Runs of frames folded by
+/// [`collapse_dependency_frames`](BacktracePrinter::collapse_dependency_frames)
+/// are passed to `collapsed_frames` instead of `frame`, repetitions folded by
+/// [`fold_recursion`](BacktracePrinter::fold_recursion) to `repeated_frames`.
+/// If all frames were filtered, `empty` is called instead of the frame
+/// callbacks.
+///
+/// [`TerminalRenderer`] is the implementation used by
+/// [`print_trace`](BacktracePrinter::print_trace). Custom renderers can use
+/// [`BacktracePrinter::is_dependency_frame`] and
+/// [`BacktracePrinter::permalink`] to present frames consistently with the
+/// built-in output.
+///
+/// # Example
+///
+/// ```rust
+/// use color_backtrace::{BacktracePrinter, Frame, TraceRenderer};
+///
+/// struct OneLine(String);
+///
+/// impl TraceRenderer for OneLine {
+///     fn frame(&mut self, frame: &Frame) -> std::io::Result<()> {
+///         let name = frame.name.as_deref().unwrap_or("<unknown>");
+///         self.0 += &format!("{} <- ", name);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut renderer = OneLine(String::new());
+/// BacktracePrinter::new()
+///     .render_trace(&backtrace::Backtrace::new(), &mut renderer)
+///     .unwrap();
+/// ```
+pub trait TraceRenderer {
+    /// Called before any other callback.
+    ///
+    /// `frames` holds all frames of the backtrace, `filtered_frames` the ones
+    /// that are about to be rendered.
+    fn header(&mut self, _frames: &[Frame], _filtered_frames: &[&Frame]) -> IOResult {
+        Ok(())
+    }
+
+    /// Called for each run of consecutive frames removed by the frame filters.
+    fn hidden_frames(&mut self, _frames: &[Frame]) -> IOResult {
+        Ok(())
+    }
+
+    /// Called for each frame that survived filtering.
+    fn frame(&mut self, frame: &Frame) -> IOResult;
+
+    /// Called for each run of adjacent dependency frames from the crate
+    /// `crate_name` that was collapsed.
+    ///
+    /// Renders the frames individually by default.
+    fn collapsed_frames(&mut self, frames: &[&Frame], _crate_name: &str) -> IOResult {
+        frames.iter().try_for_each(|frame| self.frame(frame))
+    }
+
+    /// Called with the repetitions of a sequence of `period` frames that
+    /// directly follow its first occurrence.
+    ///
+    /// Renders the frames individually by default.
+    fn repeated_frames(&mut self, frames: &[&Frame], _period: usize) -> IOResult {
+        frames.iter().try_for_each(|frame| self.frame(frame))
+    }
+
+    /// Called for each line of the source snippet of the preceding frame.
+    ///
+    /// Source snippets are only read at [`Verbosity::Full`] and if the source
+    /// file is found on disk.
+    fn source_line(&mut self, _frame: &Frame, _lineno: u32, _line: &str) -> IOResult {
+        Ok(())
+    }
+
+    /// Called instead of the frame callbacks if no frames survived filtering.
+    fn empty(&mut self) -> IOResult {
+        Ok(())
+    }
+
+    /// Called after all other callbacks.
+    fn footer(&mut self) -> IOResult {
+        Ok(())
+    }
+}
+
+/// The default renderer, producing the colored output of
+/// [`print_trace`](BacktracePrinter::print_trace).
+pub struct TerminalRenderer<'a, W> {
+    printer: &'a BacktracePrinter,
+    out: &'a mut W,
+    /// The frame whose source snippet is being highlighted, if any.
+    highlighted_frame: Option<usize>,
+    highlighter: Highlighter,
+    /// The width to lay frames out for, if any.
+    width: Option<usize>,
+}
+
+impl<'a, W: WriteColor> TerminalRenderer<'a, W> {
+    /// Create a renderer writing to `out` using the settings of `printer`.
+    pub fn new(printer: &'a BacktracePrinter, out: &'a mut W) -> Self {
+        let width = printer.width.or_else(|| {
+            if out.supports_color() {
+                layout::terminal_width()
+            } else {
+                None
+            }
+        });
+        Self::with_width(printer, out, width)
+    }
+
+    fn with_width(printer: &'a BacktracePrinter, out: &'a mut W, width: Option<usize>) -> Self {
+        Self {
+            printer,
+            out,
+            highlighted_frame: None,
+            highlighter: Highlighter::default(),
+            width: width.map(|x| x.max(layout::MIN_WIDTH)),
+        }
+    }
+
+    fn banner_width(&self) -> usize {
+        self.width.unwrap_or(layout::DEFAULT_WIDTH)
+    }
+
+    fn highlighted_source_line(&mut self, frame: &Frame, lineno: u32, line: &str) -> IOResult {
+        // Comments and strings only carry over between lines of the same snippet.
+        if self.highlighted_frame != Some(frame.n) {
+            self.highlighted_frame = Some(frame.n);
+            self.highlighter = Highlighter::default();
+        }
+
+        let colors = &self.printer.colors;
+        let is_selected = Some(lineno) == frame.lineno;
+        let base = self.source_line_spec(frame, is_selected);
+
+        self.out.set_color(&base)?;
+        write!(
+            self.out,
+            "{:>8} {} ",
+            lineno,
+            if is_selected { '>' } else { '│' }
+        )?;
+
+        for (token, text) in self.highlighter.highlight(line) {
+            let spec = match token {
+                Token::Plain => None,
+                Token::Keyword => Some(&colors.src_keyword),
+                Token::String => Some(&colors.src_string),
+                Token::Comment => Some(&colors.src_comment),
+                Token::Lifetime => Some(&colors.src_lifetime),
+                Token::Macro => Some(&colors.src_macro),
+            };
+            match spec {
+                Some(spec) => self.out.set_color(&overlay(&base, spec))?,
+                None => self.out.set_color(&base)?,
+            }
+            write!(self.out, "{}", text)?;
+        }
+
+        self.out.reset()?;
+        writeln!(self.out)?;
+        self.column_marker(frame, lineno, line)
+    }
+
+    /// The style of a source line, before highlighting.
+    fn source_line_spec(&self, frame: &Frame, is_selected: bool) -> ColorSpec {
+        let colors = &self.printer.colors;
+        let base = if self.printer.is_dependency_frame(frame) {
+            colors.dependency_src_ln.clone()
+        } else {
+            ColorSpec::new()
+        };
+
+        if is_selected {
+            overlay(&base, &colors.selected_src_ln)
+        } else {
+            base
+        }
+    }
+
+    fn column_marker(&mut self, frame: &Frame, lineno: u32, line: &str) -> IOResult {
+        if !self.printer.should_mark_column || Some(lineno) != frame.lineno {
+            return Ok(());
+        }
+
+        if let Some(marker) = frame.column_marker(line, 11) {
+            self.out.set_color(&self.printer.colors.selected_src_ln)?;
+            writeln!(self.out, "{}", marker)?;
+            self.out.reset()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Combine two color specs, with the colors of `top` taking precedence.
+fn overlay(base: &ColorSpec, top: &ColorSpec) -> ColorSpec {
+    let mut spec = base.clone();
+    if let Some(fg) = top.fg() {
+        spec.set_fg(Some(*fg));
+    }
+    if let Some(bg) = top.bg() {
+        spec.set_bg(Some(*bg));
+    }
+    spec.set_bold(base.bold() || top.bold())
+        .set_dimmed(base.dimmed() || top.dimmed())
+        .set_intense(base.intense() || top.intense())
+        .set_italic(base.italic() || top.italic())
+        .set_underline(base.underline() || top.underline());
+    spec
+}
+
+impl<W: WriteColor> TraceRenderer for TerminalRenderer<'_, W> {
+    fn header(&mut self, _frames: &[Frame], _filtered_frames: &[&Frame]) -> IOResult {
+        writeln!(
+            self.out,
+            "{:━^width$}",
+            " BACKTRACE ",
+            width = self.banner_width()
+        )
+    }
+
+    fn hidden_frames(&mut self, frames: &[Frame]) -> IOResult {
+        self.out
+            .set_color(&self.printer.colors.frames_omitted_msg)?;
+        let n = frames.len();
+        let text = format!(
+            "{decorator} {n} frame{plural} hidden {decorator}",
+            n = n,
+            plural = if n == 1 { "" } else { "s" },
+            decorator = "⋮",
+        );
+        writeln!(self.out, "{:^width$}", text, width = self.banner_width())?;
+        self.out.reset()
+    }
+
+    fn repeated_frames(&mut self, frames: &[&Frame], period: usize) -> IOResult {
+        self.out
+            .set_color(&self.printer.colors.frames_omitted_msg)?;
+        writeln!(
+            self.out,
+            "{:^width$}",
+            repetition_msg(frames.len(), period),
+            width = self.banner_width()
+        )?;
+        self.out.reset()
+    }
+
+    fn collapsed_frames(&mut self, frames: &[&Frame], crate_name: &str) -> IOResult {
+        self.out
+            .set_color(&self.printer.colors.frames_omitted_msg)?;
+        let text = format!(
+            "{decorator} {n} frames in {krate} {decorator}",
+            n = frames.len(),
+            krate = crate_name,
+            decorator = "⋮",
+        );
+        writeln!(self.out, "{:^width$}", text, width = self.banner_width())?;
+        self.out.reset()
+    }
+
+    fn frame(&mut self, frame: &Frame) -> IOResult {
+        frame.print(frame.n, self.out, self.printer, self.width)
+    }
+
+    fn source_line(&mut self, frame: &Frame, lineno: u32, line: &str) -> IOResult {
+        if self.printer.should_highlight_source {
+            return self.highlighted_source_line(frame, lineno, line);
+        }
+
+        let is_selected = Some(lineno) == frame.lineno;
+        let spec = self.source_line_spec(frame, is_selected);
+        if is_selected {
+            // Print actual source line with brighter color.
+            self.out.set_color(&spec)?;
+            writeln!(self.out, "{:>8} > {}", lineno, line)?;
+            self.out.reset()?;
+            self.column_marker(frame, lineno, line)
+        } else if spec.is_none() {
+            writeln!(self.out, "{:>8} │ {}", lineno, line)
+        } else {
+            self.out.set_color(&spec)?;
+            writeln!(self.out, "{:>8} │ {}", lineno, line)?;
+            self.out.reset()
+        }
+    }
+
+    fn empty(&mut self) -> IOResult {
+        // TODO: Would probably look better centered.
+        writeln!(self.out, "<empty backtrace>")
+    }
+}
+
+/// The line replacing `len` frames repeating a sequence of `period` frames.
+fn repetition_msg(len: usize, period: usize) -> String {
+    let times = len / period;
+    format!(
+        "{decorator} previous {frames} repeated {times} more time{plural} {decorator}",
+        frames = if period == 1 {
+            "frame".to_owned()
+        } else {
+            format!("{} frames", period)
+        },
+        times = times,
+        plural = if times == 1 { "" } else { "s" },
+        decorator = "⋮",
+    )
 }
 
 // ============================================================================================== //
@@ -825,8 +2480,306 @@ pub fn print_backtrace(trace: &backtrace::Backtrace, s: &mut BacktracePrinter) -
     since = "0.4.0",
     note = "Use `BacktracePrinter::print_panic_info` instead`"
 )]
-pub fn print_panic_info(pi: &PanicInfo, s: &mut BacktracePrinter) -> IOResult {
+pub fn print_panic_info(pi: &PanicHookInfo, s: &mut BacktracePrinter) -> IOResult {
     s.print_panic_info(pi, &mut default_output_stream())
 }
 
 // ============================================================================================== //
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{:#}` output of a `std::backtrace::Backtrace` captured in `main`, as
+    /// printed by Rust 1.95 with v0 mangled `std` symbols.
+    const STD_TRACE: &str = "\
+   0:     0x55edf49786eb - std[e28293b1aa0f68bd]::backtrace_rs::backtrace::libunwind::trace
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/../../backtrace/src/backtrace/libunwind.rs:117:9
+   1:     0x55edf49786eb - std[e28293b1aa0f68bd]::backtrace_rs::backtrace::trace_unsynchronized::<<std[e28293b1aa0f68bd]::backtrace::Backtrace>::create::{closure#0}>
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/../../backtrace/src/backtrace/mod.rs:66:14
+   2:     0x55edf49786eb - <std[e28293b1aa0f68bd]::backtrace::Backtrace>::create
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/backtrace.rs:331:13
+   3:     0x55edf4978635 - <std[e28293b1aa0f68bd]::backtrace::Backtrace>::force_capture
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/backtrace.rs:312:9
+   4:     0x55edf495d53a - app::main::h5525489dfe97df5e
+                               at /home/user/app/src/main.rs:2:22
+   5:     0x55edf495cf7b - core::ops::function::FnOnce::call_once::ha439727b0cbfca77
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/core/src/ops/function.rs:250:5
+   6:     0x55edf495d60e - std::sys::backtrace::__rust_begin_short_backtrace::h7ce0819c5ed13686
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/sys/backtrace.rs:166:18
+   7:     0x55edf495d671 - std::rt::lang_start::{{closure}}::h28cf0f4d756ee84a
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/rt.rs:206:18
+   8:     0x55edf4983b24 - std[e28293b1aa0f68bd]::rt::lang_start_internal
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/rt.rs:171:5
+   9:     0x55edf495d657 - std::rt::lang_start::h1f85e150be802b6a
+                               at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/rt.rs:205:5
+  10:     0x55edf495d5fe - main
+                               at /home/user/app/src/shim.c:12
+  11:     0x7f9815af924a - <unknown>
+  12:     0x7f9815af9305 - __libc_start_main
+                               at ./csu/../csu/libc-start.c
+  13:     0x55edf495ce81 - _start
+";
+
+    #[test]
+    fn collect_std_frames_parses_alternate_display() {
+        let frames = collect_std_frames(STD_TRACE);
+        assert_eq!(frames.len(), 14);
+        assert!(frames.iter().zip(1..).all(|(frame, n)| frame.n == n));
+
+        let main = &frames[4];
+        assert_eq!(main.name.as_deref(), Some("app::main::h5525489dfe97df5e"));
+        assert_eq!(main.ip, 0x55edf495d53a);
+        assert_eq!(
+            main.filename.as_deref(),
+            Some(Path::new("/home/user/app/src/main.rs"))
+        );
+        assert_eq!((main.lineno, main.colno), (Some(2), Some(22)));
+
+        let c_main = &frames[10];
+        assert_eq!(
+            c_main.filename.as_deref(),
+            Some(Path::new("/home/user/app/src/shim.c"))
+        );
+        assert_eq!((c_main.lineno, c_main.colno), (Some(12), None));
+
+        let unknown = &frames[11];
+        assert_eq!(unknown.name, None);
+        assert_eq!(unknown.filename, None);
+
+        let libc_start = &frames[12];
+        assert_eq!(
+            libc_start.filename.as_deref(),
+            Some(Path::new("./csu/../csu/libc-start.c"))
+        );
+        assert_eq!((libc_start.lineno, libc_start.colno), (None, None));
+    }
+
+    #[test]
+    fn scoped_guards_remove_their_own_handler() {
+        let scoped_ids =
+            || SCOPED_HANDLERS.with(|x| x.borrow().iter().map(|x| x.0).collect::<Vec<_>>());
+        let install = || BacktracePrinter::new().install_scoped(termcolor::NoColor::new(vec![]));
+
+        let outer = install();
+        let inner = install();
+        assert_eq!(scoped_ids(), vec![outer.id, inner.id]);
+
+        let inner_id = inner.id;
+        drop(outer);
+        assert_eq!(scoped_ids(), vec![inner_id]);
+        drop(inner);
+        assert!(scoped_ids().is_empty());
+    }
+
+    #[test]
+    fn source_snippet_handles_any_context() {
+        let frame = Frame {
+            n: 1,
+            name: None,
+            lineno: Some(3),
+            colno: None,
+            filename: None,
+            ip: 0,
+        };
+        let file = Path::new(file!());
+        let line_count = std::fs::read_to_string(file).unwrap().lines().count();
+
+        let snippet = frame.source_snippet(file, u32::MAX, u32::MAX).unwrap();
+        assert_eq!(snippet.len(), line_count);
+        assert_eq!(snippet[0].0, 1);
+
+        let snippet = frame.source_snippet(file, 1, 0).unwrap();
+        assert_eq!(snippet.iter().map(|x| x.0).collect::<Vec<_>>(), [2, 3]);
+
+        let frame = Frame {
+            lineno: Some(0),
+            ..frame
+        };
+        assert!(frame.source_snippet(file, 2, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn default_filter_hides_v0_mangled_std_frames() {
+        let frames = collect_std_frames(STD_TRACE);
+        assert!(frames[..4].iter().all(Frame::is_post_panic_code));
+        assert!(frames[..4].iter().all(Frame::is_dependency_code));
+        assert!(frames[8].is_dependency_code());
+        assert!(!frames[4].is_dependency_code());
+
+        let mut filtered = frames.iter().collect();
+        default_frame_filter(&mut filtered);
+        let names = filtered
+            .iter()
+            .map(|x| x.name_and_hash().0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "app::main",
+                "core::ops::function::FnOnce::call_once",
+                "std::sys::backtrace::__rust_begin_short_backtrace",
+            ]
+        );
+    }
+
+    fn frame(name: &str, filename: Option<&str>) -> Frame {
+        Frame {
+            n: 1,
+            name: Some(name.into()),
+            lineno: None,
+            colno: None,
+            filename: filename.map(PathBuf::from),
+            ip: 0,
+        }
+    }
+
+    #[test]
+    fn crate_from_registry_path() {
+        let registry = "/home/user/.cargo/registry/src/index.crates.io-6f17d22bba15001f";
+        let cases = [
+            ("serde-json-1.0.108", "serde_json", "1.0.108"),
+            ("tokio-util-0.7.0-alpha.1", "tokio_util", "0.7.0-alpha.1"),
+            ("h2-0.4.1", "h2", "0.4.1"),
+            ("foo-2-1.0.0+build.5", "foo_2", "1.0.0+build.5"),
+        ];
+        for (package, name, version) in cases {
+            let file = format!("{}/{}/src/lib.rs", registry, package);
+            let frame = frame("app::main", Some(&file));
+            assert_eq!(frame.crate_name().as_deref(), Some(name), "{}", package);
+            assert_eq!(frame.crate_version(), Some(version), "{}", package);
+        }
+    }
+
+    #[test]
+    fn crate_from_symbol_name() {
+        let cases = [
+            ("app::main::h5525489dfe97df5e", Some("app")),
+            ("<&mut regex::Regex as core::fmt::Debug>::fmt", Some("regex")),
+            ("<*const dyn foo::Bar as core::fmt::Debug>::fmt", Some("foo")),
+            ("std[e28293b1aa0f68bd]::rt::lang_start", Some("std")),
+            (
+                "<alloc[4c1d8f2e9a7b3c5d]::vec::Vec<u8> as core[c1f1a4ba060b9bfa]::clone::Clone>::clone",
+                Some("alloc"),
+            ),
+            ("main", None),
+            ("{{closure}}::foo", None),
+        ];
+        for (name, krate) in cases {
+            let frame = frame(name, Some("/home/user/app/src/main.rs"));
+            assert_eq!(frame.crate_name().as_deref(), krate, "{}", name);
+            assert_eq!(frame.crate_version(), None, "{}", name);
+        }
+    }
+
+    /// Run `find_recursion` on frames given as `(n, name)`.
+    fn recursion_in(frames: &[(usize, &str)]) -> Option<(usize, usize)> {
+        let frames = frames
+            .iter()
+            .map(|&(n, name)| Frame {
+                n,
+                ..frame(name, None)
+            })
+            .collect::<Vec<_>>();
+        find_recursion(&frames.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn find_recursion_periods() {
+        // Direct recursion.
+        assert_eq!(
+            recursion_in(&[(1, "a"), (2, "a"), (3, "a"), (4, "b")]),
+            Some((1, 2))
+        );
+        // Mutual recursion.
+        let frames = [(1, "a"), (2, "b"), (3, "a"), (4, "b"), (5, "a"), (6, "b")];
+        assert_eq!(recursion_in(&frames), Some((2, 4)));
+        // Only sequences at the start are found.
+        assert_eq!(
+            recursion_in(&[(1, "b"), (2, "a"), (3, "a"), (4, "a")]),
+            None
+        );
+    }
+
+    #[test]
+    fn find_recursion_needs_three_occurrences() {
+        assert_eq!(recursion_in(&[(1, "a"), (2, "a"), (3, "b")]), None);
+        let frames = [(1, "a"), (2, "b"), (3, "a"), (4, "b"), (5, "c"), (6, "d")];
+        assert_eq!(recursion_in(&frames), None);
+    }
+
+    #[test]
+    fn find_recursion_stops_at_hidden_frames() {
+        let frames = [(1, "a"), (2, "a"), (4, "a"), (5, "a")];
+        assert_eq!(recursion_in(&frames), None);
+        let frames = [(1, "a"), (2, "a"), (3, "a"), (5, "a"), (6, "a")];
+        assert_eq!(recursion_in(&frames), Some((1, 2)));
+    }
+
+    #[test]
+    fn find_recursion_leaves_partial_cycles() {
+        let frames = [
+            (1, "a"),
+            (2, "b"),
+            (3, "a"),
+            (4, "b"),
+            (5, "a"),
+            (6, "b"),
+            (7, "a"),
+        ];
+        assert_eq!(recursion_in(&frames), Some((2, 4)));
+    }
+
+    #[test]
+    fn shorten_cargo_paths() {
+        let shorten = |x: &str| shorten_cargo_path(Path::new(x));
+        assert_eq!(
+            shorten(
+                "/home/user/.cargo/registry/src/index.crates.io-6f17d22bba15001f/\
+                 serde-1.0.193/src/de/mod.rs"
+            )
+            .as_deref(),
+            Some("serde-1.0.193/src/de/mod.rs")
+        );
+        assert_eq!(
+            shorten(
+                "/home/user/.cargo/git/checkouts/tokio-1b2c3d4e5f6a7b8c/a1b2c3d/tokio/src/lib.rs"
+            )
+            .as_deref(),
+            Some("tokio@a1b2c3d/tokio/src/lib.rs")
+        );
+        assert_eq!(
+            shorten("/home/user/.cargo/git/checkouts/my-repo-0123abcd/main/src/lib.rs").as_deref(),
+            Some("my-repo@main/src/lib.rs")
+        );
+
+        assert_eq!(shorten("/home/user/app/src/main.rs"), None);
+        assert_eq!(
+            shorten("/rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/rt.rs"),
+            None
+        );
+        assert_eq!(shorten("src/registry/src/index.rs"), None);
+        assert_eq!(
+            shorten("/home/user/.cargo/git/checkouts/tokio-1b2c3d4e5f6a7b8c"),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_disambiguators_keeps_other_brackets() {
+        assert_eq!(
+            strip_disambiguators("<std[e28293b1aa0f68bd]::backtrace::Backtrace>::create"),
+            "<std::backtrace::Backtrace>::create"
+        );
+        assert_eq!(
+            strip_disambiguators("core[c1f1a4ba060b9bfa]::array::<impl [u8; 32]>::map"),
+            "core::array::<impl [u8; 32]>::map"
+        );
+        assert_eq!(strip_disambiguators("foo::<[T]>"), "foo::<[T]>");
+        assert_eq!(strip_disambiguators("foo[]::bar"), "foo[]::bar");
+        assert!(matches!(
+            strip_disambiguators("app::main"),
+            Cow::Borrowed("app::main")
+        ));
+    }
+}
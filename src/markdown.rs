@@ -0,0 +1,245 @@
+//! Markdown reports for pasting into issue trackers.
+//!
+//! The backtrace is placed into a collapsible `<details>` section as a frame
+//! table, followed by the source snippets in fenced `rust` code blocks. No
+//! escape codes or box-drawing characters are emitted outside of code blocks,
+//! so the report survives being pasted into GitHub-flavored Markdown.
+
+use crate::{
+    panic_payload, repetition_msg, BacktracePrinter, Frame, IOResult, PanicHookInfo, TraceRenderer,
+    Verbosity,
+};
+use std::io::Write;
+
+/// Markdown output.
+impl BacktracePrinter {
+    /// Renders a [`backtrace::Backtrace`](backtrace::Backtrace) as Markdown.
+    pub fn print_trace_markdown(
+        &self,
+        trace: &backtrace::Backtrace,
+        out: &mut impl Write,
+    ) -> IOResult {
+        self.write_trace_markdown(trace, out)
+    }
+
+    /// Format a backtrace as a Markdown `String`.
+    pub fn format_trace_to_markdown_string(
+        &self,
+        trace: &backtrace::Backtrace,
+    ) -> IOResult<String> {
+        let mut buf = vec![];
+        self.write_trace_markdown(trace, &mut buf)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    /// Renders a [`PanicInfo`](std::panic::PanicInfo) struct as a Markdown report.
+    pub fn print_panic_info_markdown(&self, pi: &PanicHookInfo, out: &mut impl Write) -> IOResult {
+        writeln!(out, "### {}", self.message)?;
+        writeln!(out)?;
+
+        let payload = panic_payload(pi);
+        let fence = fence_for(payload);
+        writeln!(out, "**Message:**")?;
+        writeln!(out)?;
+        writeln!(out, "{}text", fence)?;
+        writeln!(out, "{}", payload)?;
+        writeln!(out, "{}", fence)?;
+        writeln!(out)?;
+
+        match pi.location() {
+            Some(loc) => writeln!(
+                out,
+                "**Location:** {}",
+                Code(&format!("{}:{}", loc.file(), loc.line()))
+            )?,
+            None => writeln!(out, "**Location:** unknown")?,
+        }
+
+        if self.current_verbosity() >= Verbosity::Medium {
+            writeln!(out)?;
+            self.write_trace_markdown(&backtrace::Backtrace::new(), out)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_trace_markdown(&self, trace: &backtrace::Backtrace, out: &mut impl Write) -> IOResult {
+        self.render_trace(
+            trace,
+            &mut MarkdownRenderer {
+                printer: self,
+                out,
+                snippets: vec![],
+            },
+        )
+    }
+}
+
+/// Renders the collapsible backtrace section of the Markdown report.
+struct MarkdownRenderer<'a, W> {
+    printer: &'a BacktracePrinter,
+    out: &'a mut W,
+    /// Source snippets are collected while the frame table is written and
+    /// emitted below it in the footer.
+    snippets: Vec<(usize, String, Vec<String>)>,
+}
+
+impl<W: Write> TraceRenderer for MarkdownRenderer<'_, W> {
+    fn header(&mut self, frames: &[Frame], filtered_frames: &[&Frame]) -> IOResult {
+        writeln!(self.out, "<details>")?;
+        writeln!(
+            self.out,
+            "<summary>Backtrace ({} of {} frames shown)</summary>",
+            filtered_frames.len(),
+            frames.len()
+        )?;
+        writeln!(self.out)?;
+
+        if !filtered_frames.is_empty() {
+            writeln!(self.out, "| # | Function | Location |")?;
+            writeln!(self.out, "|--:|----------|----------|")?;
+        }
+
+        Ok(())
+    }
+
+    fn hidden_frames(&mut self, frames: &[Frame]) -> IOResult {
+        writeln!(
+            self.out,
+            "| | *⋮ {} frame{} hidden ⋮* | |",
+            frames.len(),
+            if frames.len() == 1 { "" } else { "s" }
+        )
+    }
+
+    fn repeated_frames(&mut self, frames: &[&Frame], period: usize) -> IOResult {
+        writeln!(
+            self.out,
+            "| | *{}* | |",
+            repetition_msg(frames.len(), period)
+        )
+    }
+
+    fn collapsed_frames(&mut self, frames: &[&Frame], crate_name: &str) -> IOResult {
+        writeln!(
+            self.out,
+            "| | *⋮ {} frames in {} ⋮* | |",
+            frames.len(),
+            crate_name
+        )
+    }
+
+    fn frame(&mut self, frame: &Frame) -> IOResult {
+        let (name, hash) = frame.name_and_hash();
+        let name = match hash {
+            Some(hash) if !self.printer.strip_function_hash => format!("{}{}", name, hash),
+            _ => name.to_owned(),
+        };
+
+        let location = match (&frame.filename, frame.lineno) {
+            (Some(file), Some(lineno)) => {
+                Code(&format!("{}:{}", self.printer.display_path(file), lineno)).to_string()
+            }
+            (Some(file), None) => Code(&self.printer.display_path(file)).to_string(),
+            (None, _) => "*unknown*".to_owned(),
+        };
+        let location = match self.printer.permalink(frame) {
+            Some(url) => format!("[{}](<{}>)", location, url),
+            None => location,
+        };
+
+        let badge = match frame.crate_badge() {
+            Some(badge) if self.printer.should_print_crate_badges => {
+                format!(" {}", badge.replace('|', "\\|"))
+            }
+            _ => String::new(),
+        };
+
+        // Crate code is emphasized, mirroring the distinct color in the terminal.
+        let emphasis = if self.printer.is_dependency_frame(frame) {
+            ""
+        } else {
+            "**"
+        };
+        writeln!(
+            self.out,
+            "| {} | {emphasis}{}{emphasis}{} | {} |",
+            frame.n,
+            Code(&name),
+            badge,
+            location,
+            emphasis = emphasis
+        )
+    }
+
+    fn source_line(&mut self, frame: &Frame, lineno: u32, line: &str) -> IOResult {
+        if self.snippets.last().map(|x| x.0) != Some(frame.n) {
+            let name = frame.name_and_hash().0.to_owned();
+            self.snippets.push((frame.n, name, vec![]));
+        }
+
+        let marker = if Some(lineno) == frame.lineno {
+            '>'
+        } else {
+            '│'
+        };
+        let lines = &mut self.snippets.last_mut().unwrap().2;
+        lines.push(format!("{:>8} {} {}", lineno, marker, line));
+        if marker == '>' && self.printer.should_mark_column {
+            lines.extend(frame.column_marker(line, 11));
+        }
+        Ok(())
+    }
+
+    fn empty(&mut self) -> IOResult {
+        writeln!(self.out, "*empty backtrace*")
+    }
+
+    fn footer(&mut self) -> IOResult {
+        for (n, name, lines) in &self.snippets {
+            let fence = fence_for(&lines.join("\n"));
+            writeln!(self.out)?;
+            writeln!(self.out, "**{}:** {}", n, Code(name))?;
+            writeln!(self.out)?;
+            writeln!(self.out, "{}rust", fence)?;
+            for line in lines {
+                writeln!(self.out, "{}", line)?;
+            }
+            writeln!(self.out, "{}", fence)?;
+        }
+
+        writeln!(self.out)?;
+        writeln!(self.out, "</details>")
+    }
+}
+
+/// Longest run of consecutive backticks in `s`.
+fn max_backtick_run(s: &str) -> usize {
+    s.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// A code fence that can't be terminated by the given content.
+fn fence_for(content: &str) -> String {
+    "`".repeat(max_backtick_run(content).max(2) + 1)
+}
+
+/// Displays a string as an inline code span that is safe to use in table cells.
+struct Code<'a>(&'a str);
+
+impl std::fmt::Display for Code<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let ticks = "`".repeat(max_backtick_run(self.0) + 1);
+        let pad = if self.0.starts_with('`') || self.0.ends_with('`') {
+            " "
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{ticks}{pad}{}{pad}{ticks}",
+            self.0.replace('|', "\\|"),
+            ticks = ticks,
+            pad = pad
+        )
+    }
+}
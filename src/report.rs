@@ -0,0 +1,238 @@
+//! Crash report files.
+//!
+//! Daemons running detached from a terminal often lose their stderr. When
+//! configured, the panic handler additionally persists every panic as a
+//! colorless text (and optionally JSON) file, pruning old reports to stay
+//! within the configured limits.
+
+use crate::{BacktracePrinter, IOResult, PanicHookInfo, Verbosity};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use termcolor::NoColor;
+
+/// File name prefix of all crash reports, used to tell them apart from other
+/// files when pruning.
+const REPORT_PREFIX: &str = "panic-";
+
+/// Distinguishes reports of panics happening within the same millisecond.
+static REPORT_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// Settings for persisting panics to disk.
+///
+/// See [`BacktracePrinter::crash_reports`].
+#[derive(Debug, Clone)]
+pub struct CrashReportConfig {
+    dir: PathBuf,
+    json: bool,
+    verbosity: Verbosity,
+    max_files: Option<usize>,
+    max_bytes: Option<u64>,
+}
+
+impl CrashReportConfig {
+    /// Write crash reports into `dir`, which is created if it doesn't exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            json: false,
+            verbosity: Verbosity::Medium,
+            max_files: Some(100),
+            max_bytes: None,
+        }
+    }
+
+    /// Controls whether a JSON report is written next to the text report.
+    ///
+    /// Defaults to `false`.
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// Controls the verbosity of the reports, independently of the verbosity
+    /// used for the output stream.
+    ///
+    /// Defaults to `Verbosity::Medium`.
+    pub fn verbosity(mut self, v: Verbosity) -> Self {
+        self.verbosity = v;
+        self
+    }
+
+    /// Delete the oldest reports once there are more than `max` of them.
+    ///
+    /// Defaults to `Some(100)`.
+    pub fn max_files(mut self, max: Option<usize>) -> Self {
+        self.max_files = max;
+        self
+    }
+
+    /// Delete the oldest reports once they take up more than `max` bytes.
+    ///
+    /// Defaults to `None`.
+    pub fn max_bytes(mut self, max: Option<u64>) -> Self {
+        self.max_bytes = max;
+        self
+    }
+}
+
+impl BacktracePrinter {
+    /// Persist the panic as configured via `crash_reports`, if at all.
+    pub(crate) fn write_crash_reports(&self, pi: &PanicHookInfo) -> IOResult {
+        let config = match &self.crash_reports {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+
+        let mut printer = self.clone();
+        printer.verbosity = config.verbosity;
+
+        fs::create_dir_all(&config.dir)?;
+        let stem = report_stem();
+
+        let mut out = NoColor::new(BufWriter::new(File::create(
+            config.dir.join(format!("{}.txt", stem)),
+        )?));
+        printer.print_panic_info(pi, &mut out)?;
+        out.get_mut().flush()?;
+
+        if config.json {
+            let mut out = BufWriter::new(File::create(config.dir.join(format!("{}.json", stem)))?);
+            printer.print_panic_info_json(pi, &mut out)?;
+            out.flush()?;
+        }
+
+        prune_reports(config, &stem)
+    }
+}
+
+/// Build a unique, chronologically sortable file name for a new report.
+///
+/// The process id and sequence number are padded, so reports written in the
+/// same millisecond also sort by name.
+fn report_stem() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+
+    // Convert days since the epoch to a civil date (proleptic Gregorian, UTC).
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{prefix}{:04}-{:02}-{:02}T{:02}-{:02}-{:02}.{:03}Z-{:010}-{:010}",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        now.subsec_millis(),
+        std::process::id(),
+        REPORT_SEQ.fetch_add(1, Ordering::Relaxed),
+        prefix = REPORT_PREFIX,
+    )
+}
+
+/// Delete the oldest reports until the retention limits are satisfied.
+///
+/// The text and JSON file of a report count as one report and are deleted
+/// together. The report named `current` is never deleted.
+fn prune_reports(config: &CrashReportConfig, current: &str) -> IOResult {
+    if config.max_files.is_none() && config.max_bytes.is_none() {
+        return Ok(());
+    }
+
+    // The files and total size of each report, by file stem. Stems start
+    // with the timestamp, so this is sorted oldest first.
+    let mut reports: BTreeMap<String, (Vec<String>, u64)> = BTreeMap::new();
+    for entry in fs::read_dir(&config.dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let stem = match name.rsplit_once('.') {
+            Some((stem, "txt" | "json")) if stem.starts_with(REPORT_PREFIX) => stem.to_owned(),
+            _ => continue,
+        };
+        if entry.file_type()?.is_file() {
+            let report = reports.entry(stem).or_default();
+            report.1 += entry.metadata()?.len();
+            report.0.push(name);
+        }
+    }
+
+    let mut total_bytes: u64 = reports.values().map(|x| x.1).sum();
+    let mut remaining = reports.len();
+    for (stem, (names, len)) in reports {
+        let too_many = config.max_files.is_some_and(|max| remaining > max);
+        let too_large = config.max_bytes.is_some_and(|max| total_bytes > max);
+        if !too_many && !too_large {
+            break;
+        }
+        if stem == current {
+            continue;
+        }
+
+        for name in names {
+            fs::remove_file(config.dir.join(name))?;
+        }
+        total_bytes -= len;
+        remaining -= 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_reports_deletes_text_and_json_together() {
+        let dir = std::env::temp_dir().join(format!("color-backtrace-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Reports from the same millisecond, ordered by sequence number.
+        let stems = (0..12)
+            .map(|i| {
+                format!(
+                    "{}2026-01-01T00-00-00.000Z-0000000001-{:010}",
+                    REPORT_PREFIX, i
+                )
+            })
+            .collect::<Vec<_>>();
+        for stem in &stems {
+            fs::write(dir.join(format!("{}.txt", stem)), "report").unwrap();
+            fs::write(dir.join(format!("{}.json", stem)), "{}").unwrap();
+        }
+        fs::write(dir.join("unrelated.txt"), "keep me").unwrap();
+
+        let config = CrashReportConfig::new(&dir).json(true).max_files(Some(3));
+        prune_reports(&config, &stems[11]).unwrap();
+
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut expected = stems[9..]
+            .iter()
+            .flat_map(|x| [format!("{}.json", x), format!("{}.txt", x)])
+            .collect::<Vec<_>>();
+        expected.push("unrelated.txt".to_owned());
+        assert_eq!(names, expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
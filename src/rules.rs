@@ -0,0 +1,562 @@
+//! Declarative frame filter rules.
+//!
+//! Unlike filter callbacks, rules can be supplied at runtime, so the output of
+//! deployed binaries can be tuned without recompiling them.
+
+use crate::{strip_disambiguators, BacktracePrinter, Frame, IOResult};
+use std::env;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Hide,
+    Show,
+    Dependency,
+}
+
+impl Action {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "hide" => Some(Action::Hide),
+            "show" => Some(Action::Show),
+            "dependency" => Some(Action::Dependency),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    File,
+    Crate,
+}
+
+/// A declarative frame filter rule.
+///
+/// Each rule consists of an action and a glob pattern matched against one
+/// property of a frame:
+///
+/// ```text
+/// hide name:tokio::runtime::*
+/// show file:*/src/parser/*
+/// dependency crate:vendored_*
+/// ```
+///
+/// - `hide` removes matching frames from the backtrace.
+/// - `show` keeps matching frames, even if they were removed by a frame filter
+///   or a preceding rule.
+/// - `dependency` classifies matching frames as dependency code.
+///
+/// Patterns apply to the function `name` (without the `::h<hash>` suffix of
+/// legacy mangled names and the disambiguators of v0 mangled names, e.g.
+/// `std::rt::lang_start` instead of `std[e28293b1aa0f68bd]::rt::lang_start`),
+/// the source `file` or the `crate`
+/// as determined by [`Frame::crate_name`]. `*` matches any sequence of
+/// characters, `?` any single character. If multiple `hide` and `show` rules
+/// match a frame, the last one wins.
+///
+/// Rules are added via [`BacktracePrinter::add_filter_rule`] or, without
+/// recompiling, via the `COLORBT_FILTER` and `COLORBT_FILTER_FILE` env
+/// variables (see [`FilterRule::from_env`]). Lists of rules are separated by
+/// newlines, or by `;` followed by the action of the next rule, so patterns
+/// like `name:<[u8; 32] as *` may contain `;`. Lines starting with `#` are
+/// ignored.
+///
+/// Rules are applied after the frame filters, in a separate pass over all
+/// frames, rather than as a filter callback: a `show` rule has to find the
+/// frames the filters removed, which they never hand back.
+#[derive(Debug, Clone)]
+pub struct FilterRule {
+    action: Action,
+    field: Field,
+    pattern: String,
+}
+
+impl FilterRule {
+    /// Parse a list of rules separated by newlines or `;`.
+    pub fn parse_list(rules: &str) -> IOResult<Vec<Self>> {
+        rules
+            .lines()
+            .filter(|x| !x.trim_start().starts_with('#'))
+            .flat_map(split_rules)
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Get the rules from the `COLORBT_FILTER` env variable, followed by those
+    /// in the file named by the `COLORBT_FILTER_FILE` env variable.
+    ///
+    /// Since this is used to initialize a [`BacktracePrinter`], a variable
+    /// with invalid rules is reported on stderr and ignored. Printers only
+    /// read the variables once per process, when the first one is created.
+    pub fn from_env() -> Vec<Self> {
+        let mut rules = vec![];
+
+        if let Ok(var) = env::var("COLORBT_FILTER") {
+            match Self::parse_list(&var) {
+                Ok(x) => rules.extend(x),
+                Err(e) => eprintln!("Error while parsing COLORBT_FILTER: {}", e),
+            }
+        }
+
+        if let Some(path) = env::var_os("COLORBT_FILTER_FILE") {
+            match std::fs::read_to_string(&path).and_then(|x| Self::parse_list(&x)) {
+                Ok(x) => rules.extend(x),
+                Err(e) => eprintln!("Error while reading {:?}: {}", path, e),
+            }
+        }
+
+        rules
+    }
+
+    /// The rules from the environment, read on the first call.
+    pub(crate) fn from_env_once() -> &'static [Self] {
+        static RULES: OnceLock<Vec<FilterRule>> = OnceLock::new();
+        RULES.get_or_init(Self::from_env)
+    }
+
+    fn matches(&self, frame: &Frame) -> bool {
+        let text = match self.field {
+            Field::Name => frame
+                .name
+                .as_ref()
+                .map(|_| strip_disambiguators(frame.name_and_hash().0)),
+            Field::File => frame.filename.as_ref().map(|x| x.to_string_lossy()),
+            Field::Crate => frame.crate_name(),
+        };
+        text.is_some_and(|x| glob_match(&self.pattern, &x))
+    }
+}
+
+impl FromStr for FilterRule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> IOResult<Self> {
+        let invalid = |msg| Error::new(ErrorKind::InvalidInput, format!("{} in `{}`", msg, rule));
+
+        let (action, target) = rule
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid("missing pattern"))?;
+        let action = Action::from_keyword(action)
+            .ok_or_else(|| invalid("expected `hide`, `show` or `dependency`"))?;
+
+        let (field, pattern) = target
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| invalid("missing `name:`, `file:` or `crate:`"))?;
+        let field = match field {
+            "name" => Field::Name,
+            "file" => Field::File,
+            "crate" => Field::Crate,
+            _ => return Err(invalid("expected `name:`, `file:` or `crate:`")),
+        };
+
+        Ok(Self {
+            action,
+            field,
+            pattern: pattern.to_owned(),
+        })
+    }
+}
+
+/// Ready-made sets of filter rules for common runtimes and frameworks.
+///
+/// Presets hide the frames of the listed crates, which mostly consist of
+/// executor and dispatch machinery. See
+/// [`BacktracePrinter::add_filter_preset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterPreset {
+    /// `tokio` and `tokio-util`.
+    Tokio,
+    /// `async-std` and the executor crates it is built on.
+    AsyncStd,
+    /// `rayon` and `rayon-core`.
+    Rayon,
+    /// `actix-web`, `axum`, `tower` and the `hyper` plumbing below them.
+    Web,
+    /// The `futures` crates and the `Future` glue in `core`.
+    Futures,
+    /// The `test` crate driving `#[test]` functions.
+    Libtest,
+    /// `criterion`.
+    Criterion,
+    /// `proptest`.
+    Proptest,
+}
+
+impl FilterPreset {
+    /// The rules this preset consists of.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use color_backtrace::FilterPreset::*;
+    ///
+    /// for preset in [Tokio, AsyncStd, Rayon, Web, Futures, Libtest, Criterion, Proptest] {
+    ///     assert!(!preset.rules().is_empty());
+    /// }
+    /// ```
+    pub fn rules(self) -> Vec<FilterRule> {
+        let rules = match self {
+            FilterPreset::Tokio => "hide crate:tokio; hide crate:tokio_util",
+            FilterPreset::AsyncStd => {
+                "hide crate:async_std; hide crate:async_task; hide crate:async_executor;
+                 hide crate:async_global_executor; hide crate:async_io; hide crate:blocking"
+            }
+            FilterPreset::Rayon => "hide crate:rayon; hide crate:rayon_core",
+            FilterPreset::Web => {
+                "hide crate:actix_*; hide crate:axum; hide crate:axum_core; hide crate:tower;
+                 hide crate:tower_*; hide crate:hyper; hide crate:hyper_util"
+            }
+            FilterPreset::Futures => {
+                "hide crate:futures; hide crate:futures_*; hide name:core::future::*;
+                 hide name:<core::pin::Pin<*> as core::future::future::Future>::poll"
+            }
+            FilterPreset::Libtest => "hide crate:test",
+            FilterPreset::Criterion => "hide crate:criterion; hide crate:criterion_plot",
+            FilterPreset::Proptest => "hide crate:proptest",
+        };
+        FilterRule::parse_list(rules).expect("presets are valid")
+    }
+}
+
+impl BacktracePrinter {
+    /// The rules added via `add_filter_rule`, followed by those from the
+    /// environment, so deployed binaries can override the former.
+    fn filter_rules(&self) -> impl Iterator<Item = &FilterRule> {
+        self.filter_rules.iter().chain(self.env_filter_rules)
+    }
+
+    /// Apply the `hide` and `show` rules to the output of the frame filters.
+    pub(crate) fn apply_filter_rules<'a>(
+        &self,
+        frames: &'a [Frame],
+        filtered_frames: Vec<&'a Frame>,
+    ) -> Vec<&'a Frame> {
+        if !self.filter_rules().any(|x| x.action != Action::Dependency) {
+            return filtered_frames;
+        }
+
+        // Frame numbers are 1 based indices into `frames`.
+        let mut visible = vec![false; frames.len()];
+        for frame in filtered_frames {
+            visible[frame.n - 1] = true;
+        }
+
+        for rule in self.filter_rules() {
+            let show = match rule.action {
+                Action::Hide => false,
+                Action::Show => true,
+                Action::Dependency => continue,
+            };
+            for frame in frames.iter().filter(|x| rule.matches(x)) {
+                visible[frame.n - 1] = show;
+            }
+        }
+
+        frames.iter().filter(|x| visible[x.n - 1]).collect()
+    }
+
+    /// Whether a `dependency` rule matches the frame.
+    pub(crate) fn is_marked_dependency(&self, frame: &Frame) -> bool {
+        self.filter_rules()
+            .any(|x| x.action == Action::Dependency && x.matches(frame))
+    }
+}
+
+/// Split a line into rules at each `;` that is followed by an action or
+/// nothing at all, leaving those that are part of a pattern.
+fn split_rules(line: &str) -> Vec<&str> {
+    let mut rules = vec![];
+    let mut start = 0;
+    for (i, _) in line.match_indices(';') {
+        let next = line[i + 1..].trim_start();
+        let is_separator = next.is_empty()
+            || next
+                .split_once(char::is_whitespace)
+                .is_some_and(|(x, _)| Action::from_keyword(x).is_some());
+        if is_separator {
+            rules.push(&line[start..i]);
+            start = i + 1;
+        }
+    }
+    rules.push(&line[start..]);
+    rules
+}
+
+/// Match `text` against a glob `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // Position after the last `*` in the pattern and the text position it is
+    // currently assumed to match up to, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(name: &str, filename: Option<&str>) -> Frame {
+        Frame {
+            n: 1,
+            name: Some(name.to_owned()),
+            lineno: None,
+            colno: None,
+            filename: filename.map(Into::into),
+            ip: 0,
+        }
+    }
+
+    fn is_hidden_by(preset: FilterPreset, frame: Frame) -> bool {
+        let printer = BacktracePrinter::new().add_filter_preset(preset);
+        let frames = [frame];
+        printer
+            .apply_filter_rules(&frames, frames.iter().collect())
+            .is_empty()
+    }
+
+    #[test]
+    fn presets_hide_their_frames() {
+        use FilterPreset::*;
+
+        let registry = "/home/user/.cargo/registry/src/index.crates.io-6f17d22bba15001f";
+        let cases = [
+            (
+                Tokio,
+                frame(
+                    "tokio::runtime::task::harness::Harness<T,S>::poll::h0123456789abcdef",
+                    None,
+                ),
+            ),
+            (
+                Tokio,
+                frame(
+                    "<tokio[1a2b3c4d5e6f7a8b]::runtime::task::harness::Harness<_, _>>::poll",
+                    None,
+                ),
+            ),
+            (
+                Tokio,
+                frame(
+                    "{{closure}}",
+                    Some(&format!("{}/tokio-1.38.0/src/runtime/park.rs", registry)),
+                ),
+            ),
+            (
+                Tokio,
+                frame(
+                    "{{closure}}",
+                    Some(&format!(
+                        "{}/tokio-util-0.7.11/src/codec/framed.rs",
+                        registry
+                    )),
+                ),
+            ),
+            (
+                AsyncStd,
+                frame(
+                    "async_std::task::builder::Builder::blocking::{{closure}}",
+                    None,
+                ),
+            ),
+            (
+                AsyncStd,
+                frame("async_executor::Executor::run::{{closure}}", None),
+            ),
+            (
+                Rayon,
+                frame("rayon_core::registry::WorkerThread::wait_until_cold", None),
+            ),
+            (
+                Rayon,
+                frame(
+                    "rayon::iter::plumbing::bridge_producer_consumer::helper",
+                    None,
+                ),
+            ),
+            (
+                Web,
+                frame(
+                    "actix_http::h1::dispatcher::Dispatcher<T,S,B,X,U>::poll",
+                    None,
+                ),
+            ),
+            (Web, frame("axum::routing::Router::call_with_state", None)),
+            (
+                Web,
+                frame("tower::util::oneshot::Oneshot<S,Req>::poll", None),
+            ),
+            (
+                Web,
+                frame(
+                    "hyper::proto::h1::dispatch::Dispatcher<D,Bs,I,T>::poll_loop",
+                    None,
+                ),
+            ),
+            (
+                Futures,
+                frame("futures_util::future::future::Map<Fut,F>::poll", None),
+            ),
+            (
+                Futures,
+                frame(
+                    "<core[c1f1a4ba060b9bfa]::pin::Pin<&mut app::Fut> as \
+                     core[c1f1a4ba060b9bfa]::future::future::Future>::poll",
+                    None,
+                ),
+            ),
+            (
+                Futures,
+                frame(
+                    "<core::pin::Pin<P> as core::future::future::Future>::poll::h0123456789abcdef",
+                    None,
+                ),
+            ),
+            (
+                Libtest,
+                frame(
+                    "test[4e2a7d1b5c3f6a8e]::run_test::run_test_inner::{closure#0}",
+                    None,
+                ),
+            ),
+            (
+                Libtest,
+                frame(
+                    "test::__rust_begin_short_backtrace::h0123456789abcdef",
+                    None,
+                ),
+            ),
+            (
+                Criterion,
+                frame("criterion::bencher::Bencher<M>::iter", None),
+            ),
+            (
+                Proptest,
+                frame(
+                    "proptest::test_runner::runner::TestRunner::run_one_with_replay",
+                    None,
+                ),
+            ),
+        ];
+
+        for (preset, frame) in cases {
+            let name = frame.name.clone();
+            assert!(is_hidden_by(preset, frame), "{:?} keeps {:?}", preset, name);
+        }
+    }
+
+    #[test]
+    fn presets_keep_application_frames() {
+        use FilterPreset::*;
+
+        for preset in [
+            Tokio, AsyncStd, Rayon, Web, Futures, Libtest, Criterion, Proptest,
+        ] {
+            for name in [
+                "app::main::h0123456789abcdef",
+                "<app::Fut as core::future::future::Future>::poll",
+                "tokio_like::run",
+                "testing::helper",
+            ] {
+                assert!(
+                    !is_hidden_by(preset, frame(name, Some("/home/user/app/src/main.rs"))),
+                    "{:?} hides {}",
+                    preset,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_list_splits_on_semicolons_before_actions() {
+        let rules = FilterRule::parse_list(
+            "# comment; hide name:ignored
+             hide crate:tokio;show file:*/src/*; dependency name:<[u8; 32] as *;
+             hide name:a;b",
+        )
+        .unwrap();
+
+        let parsed = rules
+            .iter()
+            .map(|x| (x.action, x.field, x.pattern.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            [
+                (Action::Hide, Field::Crate, "tokio"),
+                (Action::Show, Field::File, "*/src/*"),
+                (Action::Dependency, Field::Name, "<[u8; 32] as *"),
+                (Action::Hide, Field::Name, "a;b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_str_rejects_invalid_rules() {
+        for rule in [
+            "hide",
+            "conceal name:foo",
+            "hide symbol:foo",
+            "hide foo",
+            "Hide name:foo",
+        ] {
+            let err = rule.parse::<FilterRule>().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "{}", rule);
+            assert!(err.to_string().contains(rule), "{}", err);
+        }
+
+        assert!(FilterRule::parse_list("hide crate:x; show nothing").is_err());
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("tokio::*", "tokio::runtime::park"));
+        assert!(!glob_match("tokio::*", "tokio_util::codec"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("?", "ä"));
+
+        // Backtracking: the first `a` isn't the one to match.
+        assert!(glob_match("*a*b", "xaxab"));
+        assert!(glob_match("*a*b", "aab"));
+        assert!(!glob_match("*a*b", "xaxa"));
+        assert!(!glob_match("*a*b", "bxb"));
+        assert!(glob_match("*ab", "aaab"));
+        assert!(glob_match("*a?b*", "xaxbaab"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+}
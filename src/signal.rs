@@ -0,0 +1,237 @@
+//! Colored backtraces for fatal signals.
+//!
+//! Crashes in FFI code (segfaults in C libraries, aborts, ...) never reach the
+//! panic hook. The handler installed here prints the backtrace for those, then
+//! re-raises the signal so the process still terminates (and dumps core) as it
+//! would have without the handler. Handlers installed before, e.g. by other
+//! crash reporters, run after it.
+
+use crate::{BacktracePrinter, IOResult, Verbosity};
+use std::cell::RefCell;
+use std::sync::Mutex;
+use termcolor::WriteColor;
+
+/// The signals handled by `install_signal_handler`.
+const SIGNALS: &[libc::c_int] = &[libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGABRT];
+
+/// Size of the alternate stack the handler runs on, so that stack overflows
+/// can be reported as well. Backtraces aren't captured on smaller ones.
+const ALT_STACK_SIZE: usize = 1024 * 1024;
+
+type SignalOutput = Box<dyn WriteColor + Send>;
+
+static SIGNAL_PRINTER: Mutex<Option<(BacktracePrinter, SignalOutput)>> = Mutex::new(None);
+
+/// The actions replaced by `install_signal_handler`, restored before the
+/// signal is re-raised.
+static PREVIOUS_ACTIONS: Mutex<Vec<(libc::c_int, libc::sigaction)>> = Mutex::new(Vec::new());
+
+/// Fatal signal handling.
+impl BacktracePrinter {
+    /// Install handlers for `SIGSEGV`, `SIGBUS`, `SIGILL` and `SIGABRT` that
+    /// print the backtrace to `out` before re-raising the signal.
+    ///
+    /// The handler runs on the alternate signal stack of the crashing thread.
+    /// One large enough for capturing a backtrace is only set up for the
+    /// calling thread, other threads have to call [`install_signal_stack`]
+    /// themselves, e.g. first thing in the closure passed to
+    /// `std::thread::spawn`. Threads spawned by `std` otherwise only have the
+    /// small signal stack of the Rust runtime: crashes on them are reported
+    /// without a backtrace. Threads without any signal stack, like those
+    /// spawned by C code, run the handler on their regular stack, so stack
+    /// overflows on them crash without a report.
+    ///
+    /// Handlers installed before are called after printing the backtrace.
+    /// Installing again replaces the printer and output stream.
+    ///
+    /// Capturing and printing a backtrace isn't async-signal-safe. The process
+    /// is about to die anyway, so this is a best-effort attempt: if the crash
+    /// happens while the process holds a lock needed for printing, the report
+    /// may be incomplete or missing.
+    ///
+    /// Requires the `signal-handler` feature.
+    pub fn install_signal_handler(mut self, out: impl WriteColor + Send + 'static) -> IOResult {
+        self.is_panic_handler = true;
+        *SIGNAL_PRINTER.lock().unwrap_or_else(|e| e.into_inner()) = Some((self, Box::new(out)));
+
+        install_signal_stack()?;
+
+        let mut previous_actions = PREVIOUS_ACTIONS.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                handle_signal;
+            for &sig in SIGNALS {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handler as libc::sighandler_t;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_RESETHAND;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = std::mem::zeroed();
+                if libc::sigaction(sig, &action, &mut previous) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                // When installing again, keep what was there before the first time.
+                if previous.sa_sigaction != action.sa_sigaction {
+                    previous_actions.retain(|x| x.0 != sig);
+                    previous_actions.push((sig, previous));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_signal_info(&self, sig: libc::c_int, out: &mut impl WriteColor) -> IOResult {
+        out.set_color(&self.colors.header)?;
+        writeln!(out, "{}", self.message)?;
+        out.reset()?;
+
+        write!(out, "Signal:   ")?;
+        out.set_color(&self.colors.msg_loc_prefix)?;
+        writeln!(out, "{}", signal_name(sig))?;
+        out.reset()?;
+
+        self.print_verbosity_hints(out)?;
+
+        if self.current_verbosity() >= Verbosity::Medium {
+            if is_on_small_signal_stack() {
+                // Symbolizing would overflow the stack, losing the report.
+                writeln!(
+                    out,
+                    "No backtrace, the signal stack of this thread is too small. Call \
+                     `color_backtrace::install_signal_stack` when starting the thread."
+                )?;
+            } else {
+                self.print_trace(&backtrace::Backtrace::new(), out)?;
+            }
+        }
+
+        out.flush()
+    }
+}
+
+thread_local! {
+    /// The signal stack set up by `install_signal_stack` on this thread.
+    static SIGNAL_STACK: RefCell<Option<SignalStack>> = const { RefCell::new(None) };
+}
+
+/// A mapping used as signal stack, unmapped when its thread exits.
+struct SignalStack(*mut libc::c_void);
+
+impl Drop for SignalStack {
+    fn drop(&mut self) {
+        unsafe {
+            let mut current: libc::stack_t = std::mem::zeroed();
+            let is_active = libc::sigaltstack(std::ptr::null(), &mut current) == 0
+                && current.ss_flags & libc::SS_DISABLE == 0
+                && current.ss_sp == self.0;
+            if is_active {
+                let mut disable: libc::stack_t = std::mem::zeroed();
+                disable.ss_flags = libc::SS_DISABLE;
+                libc::sigaltstack(&disable, std::ptr::null_mut());
+            }
+            libc::munmap(self.0, ALT_STACK_SIZE);
+        }
+    }
+}
+
+/// Set up an alternate signal stack for the current thread that is large
+/// enough for printing backtraces of fatal signals, unless it already has one.
+///
+/// The Rust runtime gives every thread it spawns a signal stack for its stack
+/// overflow detection, but it is too small for symbolizing a backtrace. See
+/// [`BacktracePrinter::install_signal_handler`], which calls this for the
+/// thread installing the handler. The stack is freed when the thread exits.
+///
+/// Requires the `signal-handler` feature.
+///
+/// # Example
+///
+/// ```rust
+/// std::thread::spawn(|| {
+///     color_backtrace::install_signal_stack().unwrap();
+///     // Crashes on this thread are reported with a backtrace.
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub fn install_signal_stack() -> IOResult {
+    unsafe {
+        let mut current: libc::stack_t = std::mem::zeroed();
+        if libc::sigaltstack(std::ptr::null(), &mut current) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if current.ss_flags & libc::SS_DISABLE == 0 && current.ss_size >= ALT_STACK_SIZE {
+            return Ok(());
+        }
+
+        let stack = libc::mmap(
+            std::ptr::null_mut(),
+            ALT_STACK_SIZE,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        );
+        if stack == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut new: libc::stack_t = std::mem::zeroed();
+        new.ss_sp = stack;
+        new.ss_size = ALT_STACK_SIZE;
+        if libc::sigaltstack(&new, std::ptr::null_mut()) != 0 {
+            let err = std::io::Error::last_os_error();
+            libc::munmap(stack, ALT_STACK_SIZE);
+            return Err(err);
+        }
+
+        // A stack set up before isn't active anymore, dropping it unmaps it.
+        SIGNAL_STACK.with(|x| *x.borrow_mut() = Some(SignalStack(stack)));
+    }
+
+    Ok(())
+}
+
+/// Whether the handler runs on an alternate signal stack too small for
+/// capturing a backtrace.
+fn is_on_small_signal_stack() -> bool {
+    unsafe {
+        let mut current: libc::stack_t = std::mem::zeroed();
+        libc::sigaltstack(std::ptr::null(), &mut current) == 0
+            && current.ss_flags & libc::SS_ONSTACK != 0
+            && current.ss_size < ALT_STACK_SIZE
+    }
+}
+
+extern "C" fn handle_signal(sig: libc::c_int, _: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    // Don't wait for the lock: if it is held, we crashed while printing.
+    if let Ok(mut guard) = SIGNAL_PRINTER.try_lock() {
+        if let Some((printer, out)) = guard.as_mut() {
+            if let Err(e) = printer.print_signal_info(sig, out) {
+                eprintln!("Error while printing signal backtrace: {:?}", e);
+            }
+        }
+    }
+
+    // `SA_RESETHAND` restored the default action, but a handler installed
+    // before ours takes precedence. The signal is blocked while the handler
+    // runs, so it is delivered once we return.
+    unsafe {
+        if let Ok(previous_actions) = PREVIOUS_ACTIONS.try_lock() {
+            if let Some((_, previous)) = previous_actions.iter().find(|x| x.0 == sig) {
+                libc::sigaction(sig, previous, std::ptr::null_mut());
+            }
+        }
+        libc::raise(sig);
+    }
+}
+
+fn signal_name(sig: libc::c_int) -> &'static str {
+    match sig {
+        libc::SIGSEGV => "SIGSEGV (segmentation fault)",
+        libc::SIGBUS => "SIGBUS (bus error)",
+        libc::SIGILL => "SIGILL (illegal instruction)",
+        libc::SIGABRT => "SIGABRT (abort)",
+        _ => "<unknown signal>",
+    }
+}
@@ -0,0 +1,64 @@
+//! Locating the sources of the standard library.
+//!
+//! Frames in the standard library refer to `/rustc/<commit>/library/...`, the
+//! paths on the machine that built the toolchain. If the `rust-src` component
+//! of a toolchain built from the same commit is installed via rustup, the
+//! sources are read from its sysroot instead.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The local path of a standard library source file, if found.
+pub(crate) fn local_std_source(path: &Path) -> Option<PathBuf> {
+    let mut components = path.strip_prefix("/rustc/").ok()?.components();
+    let commit = components.next()?.as_os_str().to_str()?;
+    let rest = components.as_path().strip_prefix("library").ok()?;
+
+    // All std frames of a process share the same commit.
+    static LIBRARY: OnceLock<Option<PathBuf>> = OnceLock::new();
+    let library = LIBRARY.get_or_init(|| find_library(commit)).as_ref()?;
+
+    Some(library.join(rest)).filter(|x| x.is_file())
+}
+
+/// Find the `library` directory of the standard library sources.
+///
+/// `RUST_SRC_PATH` takes precedence, otherwise the rustup toolchains are
+/// searched for one built from `commit` that has `rust-src` installed.
+fn find_library(commit: &str) -> Option<PathBuf> {
+    if let Some(path) = env::var_os("RUST_SRC_PATH") {
+        return Some(path.into());
+    }
+
+    let rustup_home = env::var_os("RUSTUP_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|x| Path::new(&x).join(".rustup"))
+    })?;
+
+    fs::read_dir(rustup_home.join("toolchains"))
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|x| x.path().join("lib").join("rustlib"))
+        .filter(|rustlib| toolchain_commit_matches(rustlib, commit))
+        .map(|rustlib| rustlib.join("src").join("rust").join("library"))
+        .find(|library| library.is_dir())
+}
+
+/// Check the `rust` package version in the channel manifest rustup installs
+/// with each toolchain, e.g. `1.70.0 (90c541806 2023-05-31)`, against `commit`.
+fn toolchain_commit_matches(rustlib: &Path, commit: &str) -> bool {
+    let manifest = match fs::read_to_string(rustlib.join("multirust-channel-manifest.toml")) {
+        Ok(manifest) => manifest,
+        Err(_) => return false,
+    };
+
+    let short_commit = format!("({} ", commit.get(..9).unwrap_or(commit));
+    manifest
+        .lines()
+        .skip_while(|x| x.trim() != "[pkg.rust]")
+        .take_while(|x| !x.starts_with("[pkg.rust.") && !x.is_empty())
+        .any(|x| x.starts_with("version = ") && x.contains(&short_commit))
+}
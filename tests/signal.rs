@@ -0,0 +1,111 @@
+//! Fatal signals can't be caught in-process, so each test re-runs itself in a
+//! child process that crashes, and checks the report on its stderr.
+#![cfg(unix)]
+
+use color_backtrace::termcolor::{ColorChoice, StandardStream};
+use color_backtrace::{BacktracePrinter, Verbosity};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Output};
+
+/// Set for the child process, which crashes instead of checking the report.
+const CHILD_ENV: &str = "COLOR_BACKTRACE_SIGNAL_TEST_CHILD";
+
+fn is_child() -> bool {
+    std::env::var_os(CHILD_ENV).is_some()
+}
+
+/// Run the test named `test` again in a child process.
+fn run_child(test: &str) -> Output {
+    Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .output()
+        .unwrap()
+}
+
+fn install_signal_handler() {
+    BacktracePrinter::new()
+        .verbosity(Verbosity::Medium)
+        .install_signal_handler(StandardStream::stderr(ColorChoice::Never))
+        .unwrap();
+}
+
+/// Assert that the child printed a report for `name` and was killed by `sig`.
+fn assert_reported(output: &Output, sig: libc::c_int, name: &str) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(
+        stderr.contains(&format!("Signal:   {}", name)),
+        "no signal header in:\n{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(" BACKTRACE "),
+        "no backtrace in:\n{}",
+        stderr
+    );
+    assert_eq!(output.status.signal(), Some(sig), "stderr:\n{}", stderr);
+    stderr
+}
+
+#[test]
+fn segfault_is_reported() {
+    if is_child() {
+        install_signal_handler();
+        // A real fault: the handler of the Rust runtime, which is called after
+        // ours, relies on the faulting instruction being executed again.
+        unsafe { std::ptr::null_mut::<u32>().write_volatile(1) };
+        unreachable!("the signal should have killed the process");
+    }
+
+    let output = run_child("segfault_is_reported");
+    assert_reported(&output, libc::SIGSEGV, "SIGSEGV (segmentation fault)");
+}
+
+#[test]
+fn abort_is_reported() {
+    if is_child() {
+        install_signal_handler();
+        std::process::abort();
+    }
+
+    let output = run_child("abort_is_reported");
+    assert_reported(&output, libc::SIGABRT, "SIGABRT (abort)");
+}
+
+extern "C" fn previous_handler(sig: libc::c_int) {
+    const MSG: &[u8] = b"previous handler called\n";
+    unsafe {
+        libc::write(libc::STDERR_FILENO, MSG.as_ptr().cast(), MSG.len());
+        // `SA_RESETHAND` restored the default action.
+        libc::raise(sig);
+    }
+}
+
+#[test]
+fn previous_handler_is_called() {
+    if is_child() {
+        unsafe {
+            let handler: extern "C" fn(libc::c_int) = previous_handler;
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESETHAND;
+            libc::sigemptyset(&mut action.sa_mask);
+            assert_eq!(
+                libc::sigaction(libc::SIGSEGV, &action, std::ptr::null_mut()),
+                0
+            );
+        }
+        install_signal_handler();
+        unsafe { libc::raise(libc::SIGSEGV) };
+        unreachable!("the signal should have killed the process");
+    }
+
+    let output = run_child("previous_handler_is_called");
+    let stderr = assert_reported(&output, libc::SIGSEGV, "SIGSEGV (segmentation fault)");
+    let called = stderr.find("previous handler called");
+    assert!(
+        called > stderr.find(" BACKTRACE "),
+        "previous handler not called after printing:\n{}",
+        stderr
+    );
+}